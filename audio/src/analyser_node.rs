@@ -1,23 +1,148 @@
 use block::{Block, Chunk, FRAMES_PER_BLOCK_USIZE};
-use node::AudioNodeEngine;
+use fft::{Complex, RealFft};
 use node::BlockInfo;
+use node::{AudioNodeEngine, AudioNodeError};
 use node::{AudioNodeType, ChannelInfo, ChannelInterpretation};
 use std::cmp;
 use std::f32::consts::PI;
+use std::sync::mpsc::Sender;
+
+/// Options for an AnalyserNode running its own AnalysisEngine
+///
+/// https://webaudio.github.io/web-audio-api/#dictdef-analyseroptions
+#[derive(Copy, Clone, Debug)]
+pub struct AnalyserNodeOptions {
+    pub fft_size: usize,
+    pub smoothing_time_constant: f64,
+    pub min_decibels: f64,
+    pub max_decibels: f64,
+}
+
+impl AnalyserNodeOptions {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        validate_fft_size(self.fft_size)
+    }
+}
+
+impl Default for AnalyserNodeOptions {
+    fn default() -> Self {
+        AnalyserNodeOptions {
+            fft_size: 2048,
+            smoothing_time_constant: 0.8,
+            min_decibels: -100.,
+            max_decibels: -30.,
+        }
+    }
+}
+
+/// Type of message directed to AnalyserNodes running their own
+/// AnalysisEngine.
+///
+/// The getters fill the given buffer like the corresponding
+/// `AnalysisEngine` method, and send it back.
+#[derive(Debug)]
+pub enum AnalyserNodeMessage {
+    GetFloatFrequencyData(Vec<f32>, Sender<Vec<f32>>),
+    GetByteFrequencyData(Vec<u8>, Sender<Vec<u8>>),
+    GetFloatTimeDomainData(Vec<f32>, Sender<Vec<f32>>),
+    GetByteTimeDomainData(Vec<u8>, Sender<Vec<u8>>),
+    SetFftSize(usize),
+    SetSmoothingTimeConstant(f64),
+    SetMinDecibels(f64),
+    SetMaxDecibels(f64),
+}
+
+impl AnalyserNodeMessage {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        match *self {
+            AnalyserNodeMessage::SetFftSize(fft_size) => validate_fft_size(fft_size),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The FFT size must be a power of two between 32 and 32768
+///
+/// https://webaudio.github.io/web-audio-api/#dom-analysernode-fftsize
+fn validate_fft_size(fft_size: usize) -> Result<(), AudioNodeError> {
+    if fft_size < 32 || fft_size > 32768 || !fft_size.is_power_of_two() {
+        return Err(AudioNodeError::IndexSize);
+    }
+    Ok(())
+}
+
+/// Where the analysed audio goes
+enum Analysis {
+    /// Handed out block by block, to be analysed elsewhere
+    Callback(Box<FnMut(Block) + Send>),
+    /// Analysed on the rendering thread
+    Engine(Box<AnalysisEngine>),
+}
 
 #[derive(AudioNodeCommon)]
 pub(crate) struct AnalyserNode {
     channel_info: ChannelInfo,
-    callback: Box<FnMut(Block) + Send>,
+    analysis: Analysis,
 }
 
 impl AnalyserNode {
     pub fn new(callback: Box<FnMut(Block) + Send>, channel_info: ChannelInfo) -> Self {
         Self {
-            callback,
+            analysis: Analysis::Callback(callback),
             channel_info,
         }
     }
+
+    pub fn new_with_engine(options: AnalyserNodeOptions, channel_info: ChannelInfo) -> Self {
+        let engine = AnalysisEngine::new(
+            options.fft_size,
+            options.smoothing_time_constant,
+            options.min_decibels,
+            options.max_decibels,
+        );
+        Self {
+            analysis: Analysis::Engine(Box::new(engine)),
+            channel_info,
+        }
+    }
+
+    pub fn handle_message(&mut self, message: AnalyserNodeMessage, _: f32) {
+        let engine = match self.analysis {
+            Analysis::Engine(ref mut engine) => engine,
+            Analysis::Callback(_) => {
+                warn!("AnalyserNode without an engine can't be queried");
+                return;
+            }
+        };
+        match message {
+            AnalyserNodeMessage::GetFloatFrequencyData(mut dest, tx) => {
+                engine.fill_frequency_data(&mut dest);
+                let _ = tx.send(dest);
+            }
+            AnalyserNodeMessage::GetByteFrequencyData(mut dest, tx) => {
+                engine.fill_byte_frequency_data(&mut dest);
+                let _ = tx.send(dest);
+            }
+            AnalyserNodeMessage::GetFloatTimeDomainData(mut dest, tx) => {
+                engine.fill_time_domain_data(&mut dest);
+                let _ = tx.send(dest);
+            }
+            AnalyserNodeMessage::GetByteTimeDomainData(mut dest, tx) => {
+                engine.fill_byte_time_domain_data(&mut dest);
+                let _ = tx.send(dest);
+            }
+            AnalyserNodeMessage::SetFftSize(fft_size) => engine.set_fft_size(fft_size),
+            AnalyserNodeMessage::SetSmoothingTimeConstant(smoothing_constant) => {
+                engine.set_smoothing_constant(smoothing_constant)
+            }
+            AnalyserNodeMessage::SetMinDecibels(min_decibels) => {
+                engine.set_min_decibels(min_decibels)
+            }
+            AnalyserNodeMessage::SetMaxDecibels(max_decibels) => {
+                engine.set_max_decibels(max_decibels)
+            }
+        }
+    }
 }
 
 impl AudioNodeEngine for AnalyserNode {
@@ -28,14 +153,20 @@ impl AudioNodeEngine for AnalyserNode {
     fn process(&mut self, inputs: Chunk, _: &BlockInfo) -> Chunk {
         debug_assert!(inputs.len() == 1);
 
-        let mut push = inputs.blocks[0].clone();
-        push.mix(1, ChannelInterpretation::Speakers);
-
-        (self.callback)(push);
+        match self.analysis {
+            Analysis::Callback(ref mut callback) => {
+                let mut push = inputs.blocks[0].clone();
+                push.mix(1, ChannelInterpretation::Speakers);
+                callback(push)
+            }
+            Analysis::Engine(ref mut engine) => engine.push_input(&inputs.blocks[0]),
+        }
 
         // analyser node doesn't modify the inputs
         inputs
     }
+
+    make_message_handler!(AnalyserNode: handle_message);
 }
 
 /// From https://webaudio.github.io/web-audio-api/#dom-analysernode-fftsize
@@ -67,6 +198,10 @@ pub struct AnalysisEngine {
     /// The windowed time domain data
     /// Used during FFT computation
     windowed: Vec<f32>,
+    /// The FFT for the current fft_size
+    fft: RealFft,
+    /// The output of the FFT
+    spectrum: Vec<Complex>,
 }
 
 impl AnalysisEngine {
@@ -93,6 +228,8 @@ impl AnalysisEngine {
             computed_fft_data: Vec::with_capacity(fft_size / 2),
             smoothed_fft_data: Vec::with_capacity(fft_size / 2),
             windowed: Vec::with_capacity(fft_size),
+            fft: RealFft::new(fft_size),
+            spectrum: Vec::with_capacity(fft_size / 2 + 1),
         }
     }
 
@@ -102,6 +239,9 @@ impl AnalysisEngine {
         debug_assert!(fft_size & fft_size - 1 == 0);
         self.fft_size = fft_size;
         self.fft_computed = false;
+        if self.fft.size() != fft_size {
+            self.fft = RealFft::new(fft_size);
+        }
     }
 
     pub fn get_fft_size(&self) -> usize {
@@ -169,11 +309,17 @@ impl AnalysisEngine {
         }
     }
 
-    pub fn push(&mut self, mut block: Block) {
+    pub fn push(&mut self, block: Block) {
         debug_assert!(block.chan_count() == 1);
+        self.push_input(&block);
+    }
+
+    /// Push a block with any number of channels, down-mixing it to mono
+    /// the way an AnalyserNode does
+    pub fn push_input(&mut self, block: &Block) {
         self.advance();
         if !block.is_silence() {
-            self.curent_block_mut().copy_from_slice(block.data_mut());
+            block.mix_into_mono(self.curent_block_mut());
         }
         self.fft_computed = false;
     }
@@ -214,18 +360,13 @@ impl AnalysisEngine {
         self.apply_blackman_window();
         self.computed_fft_data.resize(self.fft_size / 2, 0.);
         self.smoothed_fft_data.resize(self.fft_size / 2, 0.);
+        self.spectrum
+            .resize(self.fft_size / 2 + 1, Default::default());
+        self.fft.forward(&self.windowed, &mut self.spectrum);
 
         for k in 0..(self.fft_size / 2) {
-            let mut sum_real = 0.;
-            let mut sum_imaginary = 0.;
-            let factor = -2. * PI * k as f32 / self.fft_size as f32;
-            for n in 0..(self.fft_size) {
-                sum_real += self.windowed[n] * (factor * n as f32).cos();
-                sum_imaginary += self.windowed[n] * (factor * n as f32).sin();
-            }
-            let sum_real = sum_real / self.fft_size as f32;
-            let sum_imaginary = sum_imaginary / self.fft_size as f32;
-            let magnitude = (sum_real * sum_real + sum_imaginary * sum_imaginary).sqrt();
+            let bin = self.spectrum[k] * (1. / self.fft_size as f32);
+            let magnitude = (bin.re * bin.re + bin.im * bin.im).sqrt();
             self.smoothed_fft_data[k] = (self.smoothing_constant * self.smoothed_fft_data[k] as f64
                 + (1. - self.smoothing_constant) * magnitude as f64)
                 as f32;
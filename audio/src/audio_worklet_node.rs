@@ -0,0 +1,196 @@
+use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK_USIZE};
+use node::{AudioNodeEngine, AudioNodeError, BlockInfo};
+use node::{AudioNodeType, ChannelInfo};
+use param::{Param, ParamDescriptor, ParamType};
+
+/// User-defined audio processing, run on the rendering thread
+///
+/// https://webaudio.github.io/web-audio-api/#audioworkletprocessor
+pub trait AudioWorkletProcessor: Send {
+    /// Render a block of audio
+    ///
+    /// `inputs` holds one block per input, with every channel explicitly
+    /// present, and `outputs` one silent block per output, with the
+    /// channel count given in the node options, to be written to.
+    ///
+    /// `params` holds the values of the declared params for this block, in
+    /// the order of their descriptors: FRAMES_PER_BLOCK values, or a single
+    /// one if the value doesn't change over the block.
+    ///
+    /// The return value tells whether the processor may still output sound
+    /// without any input, like a source would. Once it returns false, a
+    /// node released by the control thread is removed as soon as it has no
+    /// inputs left.
+    fn process(
+        &mut self,
+        inputs: &[Block],
+        outputs: &mut [Block],
+        params: &[Vec<f32>],
+        info: &BlockInfo,
+    ) -> bool;
+}
+
+/// https://webaudio.github.io/web-audio-api/#dictdef-audioworkletnodeoptions
+pub struct AudioWorkletNodeOptions {
+    pub processor: Box<AudioWorkletProcessor>,
+    pub number_of_inputs: u32,
+    pub number_of_outputs: u32,
+    /// The channel count of each output. If None, a node with a single
+    /// input and a single output has as many output channels as it has
+    /// input channels, and other nodes have mono outputs.
+    pub output_channel_count: Option<Vec<u8>>,
+    /// The custom AudioParams of the node, which can then be accessed as
+    /// `ParamType::Custom(index)`, `index` being the position of their
+    /// descriptor.
+    ///
+    /// https://webaudio.github.io/web-audio-api/#dictdef-audioparamdescriptor
+    pub parameters: Vec<ParamDescriptor>,
+}
+
+impl AudioWorkletNodeOptions {
+    /// Options for a node with a single input and output, and no params
+    pub fn new(processor: Box<AudioWorkletProcessor>) -> Self {
+        AudioWorkletNodeOptions {
+            processor,
+            number_of_inputs: 1,
+            number_of_outputs: 1,
+            output_channel_count: None,
+            parameters: Vec::new(),
+        }
+    }
+
+    /// https://webaudio.github.io/web-audio-api/#dom-audioworkletnode-audioworkletnode
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        if self.number_of_inputs == 0 && self.number_of_outputs == 0 {
+            return Err(AudioNodeError::NotSupported);
+        }
+        if let Some(ref counts) = self.output_channel_count {
+            if counts.contains(&0) {
+                return Err(AudioNodeError::NotSupported);
+            }
+            if counts.len() != self.number_of_outputs as usize {
+                return Err(AudioNodeError::IndexSize);
+            }
+        }
+        // https://webaudio.github.io/web-audio-api/#dom-audioworkletglobalscope-registerprocessor
+        let valid_descriptor = |descriptor: &ParamDescriptor| {
+            descriptor.min_value <= descriptor.default_value
+                && descriptor.default_value <= descriptor.max_value
+        };
+        if !self.parameters.iter().all(valid_descriptor) {
+            return Err(AudioNodeError::InvalidState);
+        }
+        Ok(())
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#AudioWorkletNode
+#[derive(AudioNodeCommon)]
+pub(crate) struct AudioWorkletNode {
+    channel_info: ChannelInfo,
+    processor: Box<AudioWorkletProcessor>,
+    number_of_inputs: u32,
+    number_of_outputs: u32,
+    output_channel_count: Option<Vec<u8>>,
+    params: Vec<Param>,
+    /// The values of each param for the current block
+    param_values: Vec<Vec<f32>>,
+    /// What the processor returned for the last block
+    keep_alive: bool,
+}
+
+impl AudioWorkletNode {
+    pub fn new(options: AudioWorkletNodeOptions, channel_info: ChannelInfo) -> Self {
+        debug_assert!(options.validate().is_ok());
+        let params = options
+            .parameters
+            .iter()
+            .map(|&descriptor| Param::new(descriptor.default_value, descriptor))
+            .collect();
+        let param_values = options
+            .parameters
+            .iter()
+            .map(|_| Vec::with_capacity(FRAMES_PER_BLOCK_USIZE))
+            .collect();
+        Self {
+            channel_info,
+            processor: options.processor,
+            number_of_inputs: options.number_of_inputs,
+            number_of_outputs: options.number_of_outputs,
+            output_channel_count: options.output_channel_count,
+            params,
+            param_values,
+            keep_alive: true,
+        }
+    }
+
+    fn output_channels(&self, output: usize, inputs: &Chunk) -> u8 {
+        match self.output_channel_count {
+            Some(ref counts) => counts[output],
+            None if self.number_of_inputs == 1 && self.number_of_outputs == 1 => {
+                inputs.blocks[0].chan_count()
+            }
+            None => 1,
+        }
+    }
+}
+
+impl AudioNodeEngine for AudioWorkletNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::AudioWorkletNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == self.number_of_inputs as usize);
+
+        for block in inputs.blocks.iter_mut() {
+            block.explicit_repeat();
+        }
+
+        for (param, values) in self.params.iter_mut().zip(&mut self.param_values) {
+            values.clear();
+            for tick in 0..FRAMES_PER_BLOCK_USIZE {
+                param.update(info, Tick(tick as u64));
+                values.push(param.value());
+            }
+            if values.iter().all(|&value| value == values[0]) {
+                values.truncate(1);
+            }
+        }
+
+        let mut outputs = Chunk::default();
+        for output in 0..self.number_of_outputs as usize {
+            let channels = self.output_channels(output, &inputs);
+            outputs.blocks.push(Block::for_channels_explicit(channels));
+        }
+
+        self.keep_alive = self.processor.process(
+            &inputs.blocks,
+            &mut outputs.blocks,
+            &self.param_values,
+            info,
+        );
+        outputs
+    }
+
+    fn input_count(&self) -> u32 {
+        self.number_of_inputs
+    }
+
+    fn output_count(&self) -> u32 {
+        self.number_of_outputs
+    }
+
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        self.keep_alive
+    }
+
+    fn get_param(&mut self, id: ParamType) -> &mut Param {
+        match id {
+            ParamType::Custom(index) if (index as usize) < self.params.len() => {
+                &mut self.params[index as usize]
+            }
+            _ => panic!("Unknown param {:?} for AudioWorkletNode", id),
+        }
+    }
+}
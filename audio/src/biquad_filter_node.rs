@@ -1,11 +1,15 @@
 use block::Chunk;
 use block::Tick;
+use block::FRAMES_PER_BLOCK;
+use iir_filter_node::frequency_response;
 use node::AudioNodeEngine;
 use node::BlockInfo;
 use node::{AudioNodeMessage, AudioNodeType, ChannelInfo};
-use param::{Param, ParamType};
+use param::{Param, ParamDescriptor, ParamType};
 use smallvec::SmallVec;
+use std::f32;
 use std::f64::consts::{SQRT_2, PI};
+use std::sync::mpsc::Sender;
 
 #[derive(Copy, Clone, Debug)]
 pub struct BiquadFilterNodeOptions {
@@ -40,9 +44,13 @@ impl Default for BiquadFilterNodeOptions {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub enum BiquadFilterNodeMessage {
     SetFilterType(FilterType),
+    /// Compute the magnitude and phase response of the filter, with its
+    /// current coefficients, at each of the given frequencies, in Hz.
+    /// Frequencies outside of [0, nyquist] get NaN for both.
+    GetFrequencyResponse(Vec<f32>, Sender<(Vec<f32>, Vec<f32>)>),
 }
 
 /// The last two input and output values, per-channel
@@ -67,6 +75,14 @@ impl BiquadState {
         self.y2 = self.y1;
         self.y1 = y;
     }
+
+    /// Whether the filter has settled, with anything left of the previous
+    /// input being too small to be heard
+    fn is_quiescent(&self) -> bool {
+        [self.x1, self.x2, self.y1, self.y2]
+            .iter()
+            .all(|v| v.abs() < f32::MIN_POSITIVE as f64)
+    }
 }
 
 /// https://webaudio.github.io/web-audio-api/#biquadfilternode
@@ -113,10 +129,16 @@ impl BiquadFilterNode {
         let mut ret = Self {
             channel_info,
             filter: options.filter,
-            frequency: Param::new(options.frequency),
-            gain: Param::new(options.gain),
-            q: Param::new(options.q),
-            detune: Param::new(options.detune),
+            frequency: Param::new(
+                options.frequency,
+                ParamDescriptor::new(350., 0., sample_rate / 2.),
+            ),
+            gain: Param::new(
+                options.gain,
+                ParamDescriptor::new(0., f32::MIN, 40. * f32::MAX.log10()),
+            ),
+            q: Param::new(options.q, ParamDescriptor::unbounded(1.)),
+            detune: Param::new(options.detune, ParamDescriptor::detune()),
             b0: 0.,
             b1: 0.,
             b2: 0.,
@@ -124,19 +146,15 @@ impl BiquadFilterNode {
             a2: 0.,
             state: SmallVec::new(),
         };
-        ret.update_coefficients(sample_rate);
+        ret.update_coefficients(Tick(0), sample_rate);
         ret
     }
 
-    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
-        let mut changed = self.frequency.update(info, tick);
-        changed |= self.detune.update(info, tick);
-        changed |= self.q.update(info, tick);
-        changed |= self.gain.update(info, tick);
-
-        if changed {
-            self.update_coefficients(info.sample_rate);
-        }
+    pub fn update_parameters(&mut self, info: &BlockInfo) -> bool {
+        let mut changed = self.frequency.update_block(info);
+        changed |= self.detune.update_block(info);
+        changed |= self.q.update_block(info);
+        changed |= self.gain.update_block(info);
         changed
     }
 
@@ -149,14 +167,15 @@ impl BiquadFilterNode {
         self.a2 = 0.;
     } 
 
-    /// Update the coefficients a1, a2, b0, b1, b2, given the sample_rate
+    /// Update the coefficients a1, a2, b0, b1, b2, given the values of the
+    /// params at a tick of the block and the sample_rate
     ///
     /// See https://webaudio.github.io/web-audio-api/#filters-characteristics
-    fn update_coefficients(&mut self, fs: f32) {
-        let g: f64 = self.gain.value().into();
-        let q: f64 = self.q.value().into();
-        let freq: f64 = self.frequency.value().into();
-        let f0: f64 = freq * (2.0_f64).powf(self.detune.value() as f64 / 1200.);
+    fn update_coefficients(&mut self, tick: Tick, fs: f32) {
+        let g: f64 = self.gain.block_value(tick).into();
+        let q: f64 = self.q.block_value(tick).into();
+        let freq: f64 = self.frequency.block_value(tick).into();
+        let f0: f64 = freq * (2.0_f64).powf(self.detune.block_value(tick) as f64 / 1200.);
         let fs: f64 = fs.into();
         // clamp to nominal range
         // https://webaudio.github.io/web-audio-api/#biquadfilternode
@@ -343,7 +362,15 @@ impl AudioNodeEngine for BiquadFilterNode {
         debug_assert!(inputs.len() == 1);
         self.state
             .resize(inputs.blocks[0].chan_count() as usize, Default::default());
-        self.update_parameters(info, Tick(0));
+        let changed = self.update_parameters(info);
+        // Constant params only need the coefficients to be computed once
+        let constant = self.frequency.is_constant()
+            && self.detune.is_constant()
+            && self.q.is_constant()
+            && self.gain.is_constant();
+        if changed && constant {
+            self.update_coefficients(Tick(0), info.sample_rate);
+        }
 
         // XXXManishearth this node has tail time, so even if the block is silence
         // we must still compute things on it. However, it is possible to become
@@ -367,7 +394,9 @@ impl AudioNodeEngine for BiquadFilterNode {
         {
             let mut iter = inputs.blocks[0].iter();
             while let Some(mut frame) = iter.next() {
-                self.update_parameters(info, frame.tick());
+                if !constant {
+                    self.update_coefficients(frame.tick(), info.sample_rate);
+                }
                 frame.mutate_with(|sample, chan| {
                     let state = &mut self.state[chan as usize];
                     let x0 = *sample as f64;
@@ -388,6 +417,10 @@ impl AudioNodeEngine for BiquadFilterNode {
         inputs
     }
 
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        !self.state.iter().all(BiquadState::is_quiescent)
+    }
+
     fn get_param(&mut self, id: ParamType) -> &mut Param {
         match id {
             ParamType::Frequency => &mut self.frequency,
@@ -403,7 +436,16 @@ impl AudioNodeEngine for BiquadFilterNode {
             AudioNodeMessage::BiquadFilterNode(m) => match m {
                 BiquadFilterNodeMessage::SetFilterType(f) => {
                     self.filter = f;
-                    self.update_coefficients(sample_rate);
+                    // with the values the params had at the end of the last block
+                    self.update_coefficients(FRAMES_PER_BLOCK - Tick(1), sample_rate);
+                }
+                BiquadFilterNodeMessage::GetFrequencyResponse(frequencies, tx) => {
+                    let _ = tx.send(frequency_response(
+                        &[self.b0, self.b1, self.b2],
+                        &[1., self.a1, self.a2],
+                        &frequencies,
+                        sample_rate,
+                    ));
                 }
             },
             _ => (),
@@ -299,49 +299,9 @@ impl Block {
                 // https://webaudio.github.io/web-audio-api/#down-mix
 
                 // mono
-                (2, 1) => {
-                    let mut v = Vec::with_capacity(FRAMES_PER_BLOCK_USIZE);
-                    for frame in 0..FRAMES_PER_BLOCK_USIZE {
-                        // output = 0.5 * (input.L + input.R);
-                        let o =
-                            0.5 * (self.data_chan_frame(frame, 0) + self.data_chan_frame(frame, 1));
-                        v.push(o);
-                    }
-                    self.buffer = v;
-                    self.channels = 1;
-                    self.repeat = false;
-                }
-                (4, 1) => {
-                    let mut v = Vec::with_capacity(FRAMES_PER_BLOCK_USIZE);
-                    for frame in 0..FRAMES_PER_BLOCK_USIZE {
-                        // output = 0.5 * (input.L + input.R + input.SL + input.SR);
-                        let o = 0.25 * (self.data_chan_frame(frame, 0)
-                            + self.data_chan_frame(frame, 1)
-                            + self.data_chan_frame(frame, 2)
-                            + self.data_chan_frame(frame, 3));
-                        v.push(o);
-                    }
-                    self.buffer = v;
-                    self.channels = 1;
-                    self.repeat = false;
-                }
-                (6, 1) => {
-                    let mut v = Vec::with_capacity(FRAMES_PER_BLOCK_USIZE);
-                    for frame in 0..FRAMES_PER_BLOCK_USIZE {
-                        // output = sqrt(0.5) * (input.L + input.R) + input.C + 0.5 * (input.SL + input.SR)
-                        let o =
-                            // sqrt(0.5) * (input.L + input.R)
-                            SQRT_2 * (self.data_chan_frame(frame, 0) +
-                                      self.data_chan_frame(frame, 1)) +
-                            // input.C
-                            self.data_chan_frame(frame, 2) +
-                            // (ignore LFE)
-                            // + 0 * self.buffer[frame + 3 * FRAMES_PER_BLOCK_USIZE]
-                            // 0.5 * (input.SL + input.SR)
-                            0.5 * (self.data_chan_frame(frame, 4) +
-                                   self.data_chan_frame(frame, 5));
-                        v.push(o);
-                    }
+                (2, 1) | (4, 1) | (6, 1) => {
+                    let mut v = vec![0.; FRAMES_PER_BLOCK_USIZE];
+                    self.mix_into_mono(&mut v);
                     self.buffer = v;
                     self.channels = 1;
                     self.repeat = false;
@@ -412,6 +372,40 @@ impl Block {
         }
     }
 
+    /// Write the block down-mixed to mono into `dest`, as
+    /// `mix(1, ChannelInterpretation::Speakers)` would, without
+    /// modifying it
+    pub fn mix_into_mono(&self, dest: &mut [f32]) {
+        debug_assert!(dest.len() == FRAMES_PER_BLOCK_USIZE);
+        for (frame, o) in dest.iter_mut().enumerate() {
+            *o = match self.channels {
+                // output = 0.5 * (input.L + input.R);
+                2 => 0.5 * (self.data_chan_frame(frame, 0) + self.data_chan_frame(frame, 1)),
+                // output = 0.5 * (input.L + input.R + input.SL + input.SR);
+                4 => {
+                    0.25 * (self.data_chan_frame(frame, 0)
+                        + self.data_chan_frame(frame, 1)
+                        + self.data_chan_frame(frame, 2)
+                        + self.data_chan_frame(frame, 3))
+                }
+                // output = sqrt(0.5) * (input.L + input.R) + input.C + 0.5 * (input.SL + input.SR)
+                6 => {
+                    // sqrt(0.5) * (input.L + input.R)
+                    SQRT_2 * (self.data_chan_frame(frame, 0) + self.data_chan_frame(frame, 1))
+                        // input.C
+                        + self.data_chan_frame(frame, 2)
+                        // (ignore LFE)
+                        // + 0 * self.buffer[frame + 3 * FRAMES_PER_BLOCK_USIZE]
+                        // 0.5 * (input.SL + input.SR)
+                        + 0.5 * (self.data_chan_frame(frame, 4) + self.data_chan_frame(frame, 5))
+                }
+                // Mono, or not a known kind of speaker configuration,
+                // which is treated as discrete
+                _ => self.data_chan_frame(frame, 0),
+            };
+        }
+    }
+
     /// Resize to add or remove channels, fill extra channels with silence
     pub fn resize_silence(&mut self, channels: u8) {
         self.explicit_repeat();
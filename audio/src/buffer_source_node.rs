@@ -1,13 +1,38 @@
-use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK};
+use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK_USIZE};
 use node::{AudioNodeEngine, AudioScheduledSourceNodeMessage, BlockInfo, OnEndedCallback};
 use node::{AudioNodeType, ChannelInfo, ShouldPlay};
-use param::{Param, ParamType};
+use param::{Param, ParamDescriptor, ParamType};
 
 /// Control messages directed to AudioBufferSourceNodes.
 #[derive(Debug, Clone)]
 pub enum AudioBufferSourceNodeMessage {
     /// Set the data block holding the audio sample data to be played.
     SetBuffer(Option<AudioBuffer>),
+    /// Set whether the region between loop_start and loop_end should be
+    /// played in a loop.
+    SetLoopEnabled(bool),
+    /// Set the playhead position, in seconds, at which looping ends.
+    SetLoopEnd(f64),
+    /// Set the playhead position, in seconds, at which looping begins.
+    SetLoopStart(f64),
+    /// Set how samples are interpolated between sample frames.
+    SetInterpolation(InterpolationType),
+    /// Schedules playback to start at the given time, from the given
+    /// offset in seconds into the buffer, for the given duration of buffer
+    /// content in seconds, or until the end if there is none.
+    /// https://webaudio.github.io/web-audio-api/#dom-audiobuffersourcenode-start
+    Start(f64, f64, Option<f64>),
+}
+
+/// How the buffer is resampled when the playhead falls between two
+/// sample frames.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InterpolationType {
+    /// Linear interpolation between the two closest sample frames.
+    Linear,
+    /// Cubic (Catmull-Rom) interpolation over the four closest sample
+    /// frames, which is smoother but more expensive.
+    Cubic,
 }
 
 /// This specifies options for constructing an AudioBufferSourceNode.
@@ -17,6 +42,8 @@ pub struct AudioBufferSourceNodeOptions {
     pub buffer: Option<AudioBuffer>,
     /// The initial value for the detune AudioParam.
     pub detune: f32,
+    /// The initial interpolation type.
+    pub interpolation: InterpolationType,
     /// The initial value for the loop_enabled attribute.
     pub loop_enabled: bool,
     /// The initial value for the loop_end attribute.
@@ -32,6 +59,7 @@ impl Default for AudioBufferSourceNodeOptions {
         AudioBufferSourceNodeOptions {
             buffer: None,
             detune: 0.,
+            interpolation: InterpolationType::Linear,
             loop_enabled: false,
             loop_end: None,
             loop_start: None,
@@ -40,18 +68,28 @@ impl Default for AudioBufferSourceNodeOptions {
     }
 }
 
+/// The parts of a start call which can only be applied when playback
+/// begins, once the buffer is known.
+struct PendingStart {
+    /// Offset into the buffer, in seconds.
+    offset: f64,
+    /// Duration of buffer content to play, in seconds.
+    duration: Option<f64>,
+    /// How far, in frames, the start tick is past the exact start time.
+    delay: f64,
+}
+
 /// AudioBufferSourceNode engine.
 /// https://webaudio.github.io/web-audio-api/#AudioBufferSourceNode
-/// XXX Implement looping
-/// XXX Implement playbackRate and related bits
 #[derive(AudioScheduledSourceNode, AudioNodeCommon)]
-#[allow(dead_code)]
 pub(crate) struct AudioBufferSourceNode {
     channel_info: ChannelInfo,
     /// A data block holding the audio sample data to be played.
     buffer: Option<AudioBuffer>,
     /// AudioParam to modulate the speed at which is rendered the audio stream.
     detune: Param,
+    /// How samples are interpolated between sample frames.
+    interpolation: InterpolationType,
     /// Indicates if the region of audio data designated by loopStart and loopEnd
     /// should be played continuously in a loop.
     loop_enabled: bool,
@@ -61,10 +99,15 @@ pub(crate) struct AudioBufferSourceNode {
     /// An playhead position where looping should begin if the loop_enabled
     /// attribute is true.
     loop_start: Option<f64>,
-    /// Playback offset.
-    playback_offset: usize,
+    /// Playhead position in the buffer, in (fractional) sample frames.
+    playhead: f64,
     /// The speed at which to render the audio stream.
     playback_rate: Param,
+    /// Start arguments waiting for playback to begin.
+    pending_start: Option<PendingStart>,
+    /// Buffer content left to play, in sample frames, if a duration was
+    /// given.
+    remaining: Option<f64>,
     /// Time at which the source should start playing.
     start_at: Option<Tick>,
     /// Time at which the source should stop playing.
@@ -78,25 +121,174 @@ impl AudioBufferSourceNode {
         Self {
             channel_info,
             buffer: options.buffer,
-            detune: Param::new(options.detune),
+            // Both params are a-rate, unlike in the spec, so that the
+            // rate can change within a block
+            detune: Param::new(options.detune, ParamDescriptor::detune()),
+            interpolation: options.interpolation,
             loop_enabled: options.loop_enabled,
             loop_end: options.loop_end,
             loop_start: options.loop_start,
-            playback_offset: 0,
-            playback_rate: Param::new(options.playback_rate),
+            playhead: 0.,
+            playback_rate: Param::new(options.playback_rate, ParamDescriptor::unbounded(1.)),
+            pending_start: None,
+            remaining: None,
             start_at: None,
             stop_at: None,
             onended_callback: None,
         }
     }
 
-    pub fn handle_message(&mut self, message: AudioBufferSourceNodeMessage, _: f32) {
+    pub fn handle_message(&mut self, message: AudioBufferSourceNodeMessage, sample_rate: f32) {
         match message {
             AudioBufferSourceNodeMessage::SetBuffer(buffer) => {
                 self.buffer = buffer;
             }
+            AudioBufferSourceNodeMessage::SetLoopEnabled(loop_enabled) => {
+                self.loop_enabled = loop_enabled;
+            }
+            AudioBufferSourceNodeMessage::SetLoopEnd(loop_end) => {
+                self.loop_end = Some(loop_end);
+            }
+            AudioBufferSourceNodeMessage::SetLoopStart(loop_start) => {
+                self.loop_start = Some(loop_start);
+            }
+            AudioBufferSourceNodeMessage::SetInterpolation(interpolation) => {
+                self.interpolation = interpolation;
+            }
+            AudioBufferSourceNodeMessage::Start(when, offset, duration) => {
+                let tick = Tick::from_time(when, sample_rate);
+                if self.start(tick) {
+                    self.pending_start = Some(PendingStart {
+                        offset: offset.max(0.),
+                        duration: duration.map(|duration| duration.max(0.)),
+                        delay: tick.0 as f64 - when * sample_rate as f64,
+                    });
+                }
+            }
+        }
+    }
+
+    /// A plain start call plays the buffer like one without an offset or
+    /// a duration, so it also goes through `apply_start`
+    fn handle_scheduled_source_message(
+        &mut self,
+        message: AudioScheduledSourceNodeMessage,
+        sample_rate: f32,
+    ) {
+        match message {
+            AudioScheduledSourceNodeMessage::Start(when) => self.handle_message(
+                AudioBufferSourceNodeMessage::Start(when, 0., None),
+                sample_rate,
+            ),
+            message => self.handle_source_node_message(message, sample_rate),
+        }
+    }
+
+    /// Place the playhead for a start call, `step` being how far it moves
+    /// in the first frame
+    fn apply_start(
+        &mut self,
+        start: PendingStart,
+        len: usize,
+        buffer_rate: f32,
+        step: f64,
+        loop_region: Option<(f64, f64)>,
+    ) {
+        let mut playhead = (start.offset * buffer_rate as f64).min(len as f64);
+        // Playing backwards from the start of the buffer, or from its end,
+        // would end right away. Start from the last frame instead, or the
+        // last frame of the loop.
+        if step < 0. && (start.offset == 0. || playhead >= len as f64) {
+            playhead = match loop_region {
+                Some((loop_start, loop_end)) => (loop_end.ceil() - 1.).max(loop_start),
+                None => len as f64 - 1.,
+            };
+        }
+        // Make up for the start time falling between two frames
+        playhead += start.delay * step;
+        // Offsets past the end of the loop start inside it
+        if let Some((loop_start, loop_end)) = loop_region {
+            if playhead >= loop_end {
+                playhead = wrap_into_loop(playhead, loop_start, loop_end);
+            }
+        }
+        self.playhead = playhead.max(0.);
+        self.remaining = start.duration.map(|duration| duration * buffer_rate as f64);
+    }
+
+    fn has_ended(&self, len: usize) -> bool {
+        !(self.playhead >= 0. && self.playhead < len as f64)
+            || self.remaining.map_or(false, |remaining| remaining <= 0.)
+    }
+
+    /// The region of the buffer to loop over, in sample frames, if looping
+    ///
+    /// If loop_start and loop_end don't describe a valid region, the whole
+    /// buffer is looped over.
+    /// https://webaudio.github.io/web-audio-api/#dom-audiobuffersourcenode-loopend
+    fn loop_region(&self, len: usize, sample_rate: f32) -> Option<(f64, f64)> {
+        if !self.loop_enabled {
+            return None;
+        }
+        let sample_rate = sample_rate as f64;
+        let len = len as f64;
+        let loop_start = self.loop_start.unwrap_or(0.);
+        let loop_end = self.loop_end.unwrap_or(0.);
+        if loop_start >= 0. && loop_end > 0. && loop_start < loop_end {
+            let start = loop_start * sample_rate;
+            let end = (loop_end * sample_rate).min(len);
+            if start < end {
+                return Some((start, end));
+            }
+        }
+        Some((0., len))
+    }
+
+    /// The speed at which the buffer is played, combining playbackRate
+    /// and detune
+    ///
+    /// https://webaudio.github.io/web-audio-api/#computedplaybackrate
+    fn computed_playback_rate(&self) -> f64 {
+        self.playback_rate.value() as f64 * 2f64.powf(self.detune.value() as f64 / 1200.)
+    }
+}
+
+/// Move a playhead which went past either end of the loop back into it
+fn wrap_into_loop(playhead: f64, loop_start: f64, loop_end: f64) -> f64 {
+    let length = loop_end - loop_start;
+    let mut offset = (playhead - loop_start) % length;
+    if offset < 0. {
+        offset += length;
+    }
+    loop_start + offset
+}
+
+/// The index of the sample frame `offset` frames away from the one at
+/// `index`, going around the loop if the playhead is inside it, and
+/// clamped to the buffer otherwise
+fn frame_index(
+    index: isize,
+    offset: isize,
+    len: usize,
+    loop_frames: Option<(isize, isize)>,
+) -> usize {
+    let mut neighbour = index + offset;
+    if let Some((start, end)) = loop_frames {
+        if offset > 0 && neighbour >= end {
+            neighbour -= end - start;
+        } else if offset < 0 && neighbour < start {
+            neighbour += end - start;
         }
     }
+    neighbour.max(0).min(len as isize - 1) as usize
+}
+
+/// The Catmull-Rom spline through p1 and p2, at f in [0, 1) between them
+pub(crate) fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, f: f32) -> f32 {
+    let a = 3. * (p1 - p2) + p3 - p0;
+    let b = 2. * p0 - 5. * p1 + 4. * p2 - p3;
+    let c = p2 - p0;
+    p1 + 0.5 * f * (c + f * (b + f * a))
 }
 
 impl AudioNodeEngine for AudioBufferSourceNode {
@@ -108,6 +300,16 @@ impl AudioNodeEngine for AudioBufferSourceNode {
         0
     }
 
+    fn has_remaining_output(&self, info: &BlockInfo) -> bool {
+        match self.buffer {
+            Some(ref buffer) => {
+                self.plays_after(info.frame + Tick::FRAMES_PER_BLOCK)
+                    && !self.has_ended(buffer.len())
+            }
+            None => false,
+        }
+    }
+
     fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
         debug_assert!(inputs.len() == 0);
 
@@ -118,13 +320,13 @@ impl AudioNodeEngine for AudioBufferSourceNode {
 
         let len = { self.buffer.as_ref().unwrap().len() as usize };
 
-        if self.playback_offset >= len {
+        if self.has_ended(len) {
             self.maybe_trigger_onended_callback();
             inputs.blocks.push(Default::default());
             return inputs;
         }
 
-        let (start_at, mut stop_at) = match self.should_play_at(info.frame) {
+        let (start_at, stop_at) = match self.should_play_at(info.frame) {
             ShouldPlay::No => {
                 inputs.blocks.push(Default::default());
                 return inputs;
@@ -132,38 +334,93 @@ impl AudioNodeEngine for AudioBufferSourceNode {
             ShouldPlay::Between(start, end) => (start.0 as usize, end.0 as usize)
         };
 
-        let buffer = self.buffer.as_ref().unwrap();
+        // Both params may be a-rate, so the rate is computed for each
+        // frame, including the ones we don't output anything for
+        let mut rates = [0.; FRAMES_PER_BLOCK_USIZE];
+        let mut rate = self.computed_playback_rate();
+        for (tick, r) in rates.iter_mut().enumerate() {
+            let tick = Tick(tick as u64);
+            let mut changed = self.playback_rate.update(info, tick);
+            changed |= self.detune.update(info, tick);
+            if changed {
+                rate = self.computed_playback_rate();
+            }
+            *r = rate;
+        }
 
-        if self.playback_offset + stop_at - start_at > len {
-            stop_at = start_at + len - self.playback_offset;
+        // The playhead moves through the buffer's own sample frames, which
+        // may not be at the context's rate
+        let buffer_rate = {
+            let buffer = self.buffer.as_ref().unwrap();
+            buffer.sample_rate.unwrap_or(info.sample_rate)
+        };
+        let rate_scale = buffer_rate as f64 / info.sample_rate as f64;
+        let loop_region = self.loop_region(len, buffer_rate);
+        if let Some(start) = self.pending_start.take() {
+            let step = rates[start_at] * rate_scale;
+            self.apply_start(start, len, buffer_rate, step, loop_region);
         }
-        let samples_to_copy = stop_at - start_at;
 
-        let next_offset = self.playback_offset + samples_to_copy;
-        if samples_to_copy == FRAMES_PER_BLOCK.0 as usize {
-            // copy entire chan
-            let mut block = Block::empty();
-            for chan in 0..buffer.chans() {
-                block.push_chan(&buffer.buffers[chan as usize][self.playback_offset..next_offset]);
+        let buffer = self.buffer.as_ref().unwrap();
+        let mut block = Block::for_channels_explicit(buffer.chans());
+        for frame in start_at..stop_at {
+            // Playing backwards ends at the start of the buffer
+            if self.has_ended(len) {
+                break;
+            }
+            let playhead = self.playhead;
+            let loop_frames = loop_region.and_then(|(loop_start, loop_end)| {
+                if playhead >= loop_start && playhead < loop_end {
+                    Some((loop_start.ceil() as isize, loop_end.ceil() as isize))
+                } else {
+                    None
+                }
+            });
+            let index = playhead.floor();
+            let f = (playhead - index) as f32;
+            let index = index as isize;
+            let at = |offset| frame_index(index, offset, len, loop_frames);
+            match self.interpolation {
+                InterpolationType::Linear => {
+                    let (i0, i1) = (at(0), at(1));
+                    for chan in 0..buffer.chans() {
+                        let data = &buffer.buffers[chan as usize];
+                        block.data_chan_mut(chan)[frame] = data[i0] + (data[i1] - data[i0]) * f;
+                    }
+                }
+                InterpolationType::Cubic => {
+                    let (i0, i1, i2, i3) = (at(-1), at(0), at(1), at(2));
+                    for chan in 0..buffer.chans() {
+                        let data = &buffer.buffers[chan as usize];
+                        block.data_chan_mut(chan)[frame] =
+                            catmull_rom(data[i0], data[i1], data[i2], data[i3], f);
+                    }
+                }
+            }
+
+            let step = rates[frame] * rate_scale;
+            self.playhead += step;
+            if let Some(ref mut remaining) = self.remaining {
+                *remaining -= step.abs();
             }
-            inputs.blocks.push(block)
-        } else {
-            // silent fill and copy
-            let mut block = Block::default();
-            block.repeat(buffer.chans());
-            block.explicit_repeat();
-            for chan in 0..buffer.chans() {
-                let data = block.data_chan_mut(chan);
-                let (_, data) = data.split_at_mut(start_at);
-                let (data, _) = data.split_at_mut(samples_to_copy);
-                data.copy_from_slice(
-                    &buffer.buffers[chan as usize][self.playback_offset..next_offset],
-                );
+            // Once past the end of the loop in the direction of playback,
+            // go back to its other side
+            if let Some((loop_start, loop_end)) = loop_region {
+                if (step > 0. && self.playhead >= loop_end)
+                    || (step < 0. && self.playhead < loop_start)
+                {
+                    self.playhead = wrap_into_loop(self.playhead, loop_start, loop_end);
+                }
             }
-            inputs.blocks.push(block)
         }
 
-        self.playback_offset = next_offset;
+        // The node may be removed right after this block if it was
+        // released, so it can't wait for the next one to fire onended
+        if self.has_ended(len) {
+            self.maybe_trigger_onended_callback();
+        }
+
+        inputs.blocks.push(block);
         inputs
     }
 
@@ -177,7 +434,7 @@ impl AudioNodeEngine for AudioBufferSourceNode {
 
     make_message_handler!(
         AudioBufferSourceNode: handle_message,
-        AudioScheduledSourceNode: handle_source_node_message
+        AudioScheduledSourceNode: handle_scheduled_source_message
     );
 }
 
@@ -185,6 +442,9 @@ impl AudioNodeEngine for AudioBufferSourceNode {
 pub struct AudioBuffer {
     /// Invariant: all buffers must be of the same length
     pub buffers: Vec<Vec<f32>>,
+    /// The rate at which the buffer was sampled, if it isn't the rate
+    /// of the context it is played in
+    pub sample_rate: Option<f32>,
 }
 
 impl AudioBuffer {
@@ -193,7 +453,10 @@ impl AudioBuffer {
         let mut buffers = Vec::with_capacity(chan as usize);
         let single = vec![0.; len];
         buffers.resize(chan as usize, single);
-        AudioBuffer { buffers }
+        AudioBuffer {
+            buffers,
+            sample_rate: None,
+        }
     }
 
     pub fn from_buffers(buffers: Vec<Vec<f32>>) -> Self {
@@ -201,7 +464,10 @@ impl AudioBuffer {
             assert_eq!(buf.len(), buffers[0].len())
         }
 
-        Self { buffers }
+        Self {
+            buffers,
+            sample_rate: None,
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -219,7 +485,7 @@ impl AudioBuffer {
 
 impl From<Vec<f32>> for AudioBuffer {
     fn from(vec: Vec<f32>) -> Self {
-        Self { buffers: vec![vec] }
+        AudioBuffer::from_buffers(vec![vec])
     }
 }
 
@@ -0,0 +1,103 @@
+use block::Chunk;
+use block::Tick;
+use node::{AudioNodeEngine, AudioScheduledSourceNodeMessage, BlockInfo, OnEndedCallback};
+use node::{AudioNodeType, ChannelInfo, ShouldPlay};
+use param::{Param, ParamDescriptor, ParamType};
+
+#[derive(Copy, Clone, Debug)]
+pub struct ConstantSourceNodeOptions {
+    pub offset: f32,
+}
+
+impl Default for ConstantSourceNodeOptions {
+    fn default() -> Self {
+        ConstantSourceNodeOptions { offset: 1. }
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#ConstantSourceNode
+#[derive(AudioScheduledSourceNode, AudioNodeCommon)]
+pub(crate) struct ConstantSourceNode {
+    channel_info: ChannelInfo,
+    offset: Param,
+    /// Time at which the source should start playing.
+    start_at: Option<Tick>,
+    /// Time at which the source should stop playing.
+    stop_at: Option<Tick>,
+    /// The ended event callback.
+    onended_callback: Option<OnEndedCallback>,
+}
+
+impl ConstantSourceNode {
+    pub fn new(options: ConstantSourceNodeOptions, channel_info: ChannelInfo) -> Self {
+        Self {
+            channel_info,
+            offset: Param::new(options.offset, ParamDescriptor::unbounded(1.)),
+            start_at: None,
+            stop_at: None,
+            onended_callback: None,
+        }
+    }
+
+    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
+        self.offset.update(info, tick)
+    }
+}
+
+impl AudioNodeEngine for ConstantSourceNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::ConstantSourceNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 0);
+
+        inputs.blocks.push(Default::default());
+
+        let (start_at, stop_at) = match self.should_play_at(info.frame) {
+            ShouldPlay::No => {
+                return inputs;
+            }
+            ShouldPlay::Between(start, end) => (start, end),
+        };
+
+        {
+            inputs.blocks[0].explicit_silence();
+            let mut iter = inputs.blocks[0].iter();
+            let mut offset = self.offset.value();
+
+            while let Some(mut frame) = iter.next() {
+                let tick = frame.tick();
+                // The param must see every tick of the block, including
+                // the ones we don't output anything for
+                if self.update_parameters(info, tick) {
+                    offset = self.offset.value();
+                }
+                if tick < start_at {
+                    continue;
+                } else if tick >= stop_at {
+                    break;
+                }
+                frame.mutate_with(|sample, _| *sample = offset);
+            }
+        }
+        inputs
+    }
+
+    fn input_count(&self) -> u32 {
+        0
+    }
+
+    fn has_remaining_output(&self, info: &BlockInfo) -> bool {
+        self.plays_after(info.frame + Tick::FRAMES_PER_BLOCK)
+    }
+
+    fn get_param(&mut self, id: ParamType) -> &mut Param {
+        match id {
+            ParamType::Offset => &mut self.offset,
+            _ => panic!("Unknown param {:?} for ConstantSourceNode", id),
+        }
+    }
+
+    make_message_handler!(AudioScheduledSourceNode: handle_source_node_message);
+}
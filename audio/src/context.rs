@@ -1,6 +1,6 @@
 use decoder::{AudioDecoder, AudioDecoderCallbacks, AudioDecoderOptions};
 use graph::{AudioGraph, InputPort, NodeId, OutputPort, PortId};
-use node::{AudioNodeInit, AudioNodeMessage, ChannelInfo};
+use node::{AudioNodeError, AudioNodeInit, AudioNodeMessage, ChannelInfo};
 use render_thread::AudioRenderThread;
 use render_thread::AudioRenderThreadMsg;
 use std::cell::Cell;
@@ -172,12 +172,24 @@ impl<B: AudioBackend + 'static> AudioContext<B> {
         rx.recv().unwrap()
     }
 
+    /// Create a node, panicking if its options are invalid
     pub fn create_node(&self, node_type: AudioNodeInit, ch: ChannelInfo) -> NodeId {
+        self.try_create_node(node_type, ch)
+            .expect("Invalid node options")
+    }
+
+    /// Create a node, unless its options are invalid
+    pub fn try_create_node(
+        &self,
+        node_type: AudioNodeInit,
+        ch: ChannelInfo,
+    ) -> Result<NodeId, AudioNodeError> {
+        node_type.validate()?;
         let (tx, rx) = mpsc::channel();
         let _ = self
             .sender
             .send(AudioRenderThreadMsg::CreateNode(node_type, tx, ch));
-        rx.recv().unwrap()
+        Ok(rx.recv().unwrap())
     }
 
     /// Resume audio processing.
@@ -189,8 +201,22 @@ impl<B: AudioBackend + 'static> AudioContext<B> {
     /// Stop audio processing and close render thread.
     make_state_change!(close, Closed, Close);
 
+    /// Send a message to a node, invalid messages being ignored
     pub fn message_node(&self, id: NodeId, msg: AudioNodeMessage) {
+        if let Err(e) = self.try_message_node(id, msg) {
+            warn!("Ignoring message for {:?}: {:?}", id, e);
+        }
+    }
+
+    /// Send a message to a node, unless it is invalid
+    pub fn try_message_node(
+        &self,
+        id: NodeId,
+        msg: AudioNodeMessage,
+    ) -> Result<(), AudioNodeError> {
+        msg.validate()?;
         let _ = self.sender.send(AudioRenderThreadMsg::MessageNode(id, msg));
+        Ok(())
     }
 
     pub fn connect_ports(&self, from: PortId<OutputPort>, to: PortId<InputPort>) {
@@ -250,6 +276,27 @@ impl<B: AudioBackend + 'static> AudioContext<B> {
             .send(AudioRenderThreadMsg::DisconnectOutputBetweenTo(out, inp));
     }
 
+    /// Remove a node from the graph right away, along with all of its
+    /// connections
+    ///
+    /// The node must not be used afterwards, its id may be given to
+    /// another node.
+    pub fn remove_node(&self, node: NodeId) {
+        let _ = self.sender.send(AudioRenderThreadMsg::RemoveNode(node));
+    }
+
+    /// Tell the rendering thread that the node isn't referenced anymore, to
+    /// be called when the control thread drops its handle to it
+    ///
+    /// The node keeps playing until it has no inputs left and stops
+    /// actively processing, and is then removed from the graph. It must
+    /// not be used afterwards.
+    ///
+    /// https://webaudio.github.io/web-audio-api/#actively-processing
+    pub fn release_node(&self, node: NodeId) {
+        let _ = self.sender.send(AudioRenderThreadMsg::ReleaseNode(node));
+    }
+
     /// Asynchronously decodes the audio file data contained in the given
     /// buffer.
     pub fn decode_audio_data(
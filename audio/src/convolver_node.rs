@@ -0,0 +1,381 @@
+use block::{Block, Chunk, FRAMES_PER_BLOCK_USIZE};
+use buffer_source_node::AudioBuffer;
+use fft::{Complex, Fft};
+use node::{AudioNodeEngine, AudioNodeError, BlockInfo};
+use node::{AudioNodeType, ChannelInfo, ChannelInterpretation};
+
+/// The FFT size used for every partition. The impulse response is split
+/// in partitions of one render quantum, which are zero padded to twice
+/// that, as required by overlap-save.
+const FFT_SIZE: usize = 2 * FRAMES_PER_BLOCK_USIZE;
+/// Number of meaningful bins in the FFT of real data, the others
+/// are the complex conjugates of these.
+const BINS: usize = FFT_SIZE / 2 + 1;
+
+/// Control messages directed to ConvolverNodes.
+#[derive(Debug, Clone)]
+pub enum ConvolverNodeMessage {
+    /// Set the impulse response. The normalize flag is taken into
+    /// account at this point.
+    SetBuffer(Option<AudioBuffer>),
+    /// Set the normalize flag used for the next impulse response.
+    SetNormalize(bool),
+}
+
+impl ConvolverNodeMessage {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        match *self {
+            ConvolverNodeMessage::SetBuffer(ref buffer) => validate_buffer(buffer),
+            ConvolverNodeMessage::SetNormalize(_) => Ok(()),
+        }
+    }
+}
+
+/// This specifies options for constructing a ConvolverNode.
+#[derive(Debug, Clone)]
+pub struct ConvolverNodeOptions {
+    /// The impulse response. Must have one, two or four channels.
+    pub buffer: Option<AudioBuffer>,
+    /// Whether the impulse response is scaled by an equal-power normalization.
+    pub normalize: bool,
+}
+
+impl ConvolverNodeOptions {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        validate_buffer(&self.buffer)
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#dom-convolvernode-buffer
+fn validate_buffer(buffer: &Option<AudioBuffer>) -> Result<(), AudioNodeError> {
+    match *buffer {
+        Some(ref buffer) if ![1, 2, 4].contains(&buffer.chans()) => {
+            Err(AudioNodeError::NotSupported)
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Default for ConvolverNodeOptions {
+    fn default() -> Self {
+        ConvolverNodeOptions {
+            buffer: None,
+            normalize: true,
+        }
+    }
+}
+
+/// The impulse response, in the frequency domain
+struct Kernel {
+    /// For each channel of the impulse response, the spectrum
+    /// of each partition
+    channels: Vec<Vec<Vec<Complex>>>,
+    partitions: usize,
+}
+
+impl Kernel {
+    fn new(buffer: &AudioBuffer, normalize: bool, fft: &Fft, sample_rate: f32) -> Self {
+        debug_assert!(buffer.chans() == 1 || buffer.chans() == 2 || buffer.chans() == 4);
+        let scale = if normalize {
+            normalization_scale(buffer, sample_rate)
+        } else {
+            1.
+        };
+        let partitions = (buffer.len() + FRAMES_PER_BLOCK_USIZE - 1) / FRAMES_PER_BLOCK_USIZE;
+        let partitions = if partitions == 0 { 1 } else { partitions };
+        let mut scratch = vec![Complex::default(); FFT_SIZE];
+        let channels = buffer
+            .buffers
+            .iter()
+            .map(|data| {
+                (0..partitions)
+                    .map(|partition| {
+                        let start = partition * FRAMES_PER_BLOCK_USIZE;
+                        for (i, x) in scratch.iter_mut().enumerate() {
+                            let sample = if i < FRAMES_PER_BLOCK_USIZE {
+                                data.get(start + i).cloned().unwrap_or(0.)
+                            } else {
+                                0.
+                            };
+                            *x = Complex::new(sample * scale, 0.);
+                        }
+                        fft.forward(&mut scratch);
+                        scratch[..BINS].to_vec()
+                    })
+                    .collect()
+            })
+            .collect();
+        Kernel {
+            channels,
+            partitions,
+        }
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#dom-convolvernode-normalize
+fn normalization_scale(buffer: &AudioBuffer, sample_rate: f32) -> f32 {
+    const GAIN_CALIBRATION: f32 = 0.00125;
+    const GAIN_CALIBRATION_SAMPLE_RATE: f32 = 44100.;
+    const MIN_POWER: f32 = 0.000125;
+
+    let mut power: f32 = buffer
+        .buffers
+        .iter()
+        .flat_map(|chan| chan.iter())
+        .map(|x| x * x)
+        .sum();
+    power = (power / (buffer.chans() as usize * buffer.len()) as f32).sqrt();
+    if !power.is_finite() || power < MIN_POWER {
+        power = MIN_POWER;
+    }
+    let mut scale = GAIN_CALIBRATION / power;
+    // The buffer has the same sample rate as the context
+    scale *= GAIN_CALIBRATION_SAMPLE_RATE / sample_rate;
+    if buffer.chans() == 4 {
+        scale *= 0.5;
+    }
+    scale
+}
+
+/// The recent history of one input channel
+struct InputHistory {
+    /// The input from the previous block
+    previous: Vec<f32>,
+    /// The spectra of the last `partitions` blocks of input, used as a
+    /// ring buffer indexed by `ConvolverNode::position`
+    spectra: Vec<Vec<Complex>>,
+}
+
+impl InputHistory {
+    fn new(partitions: usize) -> Self {
+        InputHistory {
+            previous: vec![0.; FRAMES_PER_BLOCK_USIZE],
+            spectra: vec![vec![Complex::default(); BINS]; partitions],
+        }
+    }
+
+    fn clear(&mut self) {
+        for x in self.previous.iter_mut() {
+            *x = 0.
+        }
+        for spectrum in self.spectra.iter_mut() {
+            for x in spectrum.iter_mut() {
+                *x = Complex::default();
+            }
+        }
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#ConvolverNode
+///
+/// This uses uniformly partitioned convolution: the impulse response is
+/// split into partitions of one render quantum, each of which is convolved
+/// with the matching block of past input in the frequency domain. This
+/// costs one forward FFT per input channel and one inverse FFT per output
+/// channel for each block, whatever the length of the impulse response.
+#[derive(AudioNodeCommon)]
+pub(crate) struct ConvolverNode {
+    channel_info: ChannelInfo,
+    normalize: bool,
+    kernel: Option<Kernel>,
+    fft: Fft,
+    inputs: Vec<InputHistory>,
+    /// The index of the current block in the input histories
+    position: usize,
+    /// The number of silent blocks received in a row. Once it exceeds
+    /// the number of partitions, the output is silent too.
+    silent_blocks: usize,
+    /// Scratch space for the FFTs
+    scratch: Vec<Complex>,
+    /// Scratch space where output spectra are accumulated
+    accumulator: Vec<Complex>,
+}
+
+impl ConvolverNode {
+    pub fn new(options: ConvolverNodeOptions, channel_info: ChannelInfo, sample_rate: f32) -> Self {
+        let mut node = Self {
+            channel_info,
+            normalize: options.normalize,
+            kernel: None,
+            fft: Fft::new(FFT_SIZE),
+            inputs: Vec::new(),
+            position: 0,
+            silent_blocks: 0,
+            scratch: vec![Complex::default(); FFT_SIZE],
+            accumulator: vec![Complex::default(); BINS],
+        };
+        node.set_buffer(options.buffer, sample_rate);
+        node
+    }
+
+    fn set_buffer(&mut self, buffer: Option<AudioBuffer>, sample_rate: f32) {
+        self.kernel = buffer.map(|b| Kernel::new(&b, self.normalize, &self.fft, sample_rate));
+        // The history layout depends on the number of partitions,
+        // start over
+        self.inputs.clear();
+        self.position = 0;
+        self.silent_blocks = 0;
+    }
+
+    pub fn handle_message(&mut self, message: ConvolverNodeMessage, sample_rate: f32) {
+        match message {
+            ConvolverNodeMessage::SetBuffer(buffer) => self.set_buffer(buffer, sample_rate),
+            ConvolverNodeMessage::SetNormalize(normalize) => self.normalize = normalize,
+        }
+    }
+
+    /// Transform the current block of an input channel and
+    /// store it in its history
+    fn push_input(&mut self, chan: usize, data: Option<&[f32]>) {
+        let history = &mut self.inputs[chan];
+        for (i, x) in self.scratch.iter_mut().enumerate() {
+            let sample = if i < FRAMES_PER_BLOCK_USIZE {
+                history.previous[i]
+            } else {
+                data.map_or(0., |d| d[i - FRAMES_PER_BLOCK_USIZE])
+            };
+            *x = Complex::new(sample, 0.);
+        }
+        self.fft.forward(&mut self.scratch);
+        history.spectra[self.position].copy_from_slice(&self.scratch[..BINS]);
+        match data {
+            Some(data) => history.previous.copy_from_slice(data),
+            None => {
+                for x in history.previous.iter_mut() {
+                    *x = 0.
+                }
+            }
+        }
+    }
+
+    /// Add the convolution of an input channel with a channel
+    /// of the impulse response to the accumulator
+    fn accumulate(&mut self, input: usize, kernel_chan: usize) {
+        let kernel = self.kernel.as_ref().expect("Only called with a kernel");
+        let partitions = kernel.partitions;
+        let history = &self.inputs[input];
+        for (partition, kernel) in kernel.channels[kernel_chan].iter().enumerate() {
+            // The spectrum of the input from `partition` blocks ago
+            let index = (self.position + partitions - partition) % partitions;
+            let spectrum = &history.spectra[index];
+            for ((acc, x), h) in self
+                .accumulator
+                .iter_mut()
+                .zip(spectrum.iter())
+                .zip(kernel.iter())
+            {
+                *acc += *x * *h;
+            }
+        }
+    }
+
+    /// Turn the accumulator into a channel of output
+    fn flush_accumulator(&mut self, dest: &mut [f32]) {
+        self.scratch[..BINS].copy_from_slice(&self.accumulator);
+        for bin in 1..(FFT_SIZE / 2) {
+            self.scratch[FFT_SIZE - bin] = self.accumulator[bin].conj();
+        }
+        self.fft.inverse(&mut self.scratch);
+        // overlap-save: the first half is aliased, keep the second
+        for (d, x) in dest
+            .iter_mut()
+            .zip(self.scratch[FRAMES_PER_BLOCK_USIZE..].iter())
+        {
+            *d = x.re;
+        }
+        for x in self.accumulator.iter_mut() {
+            *x = Complex::default();
+        }
+    }
+}
+
+impl AudioNodeEngine for ConvolverNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::ConvolverNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, _: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+
+        let (kernel_chans, partitions) = match self.kernel {
+            Some(ref kernel) => (kernel.channels.len(), kernel.partitions),
+            None => {
+                inputs.blocks[0] = Block::default();
+                return inputs;
+            }
+        };
+
+        if inputs.blocks[0].is_silence() {
+            self.silent_blocks += 1;
+            if self.silent_blocks > partitions {
+                // Everything in the output is silence, and the history
+                // may only hold the tail of the last input, which has
+                // been fully output. Clear it so that it doesn't show up
+                // again once the input resumes.
+                if self.silent_blocks == partitions + 1 {
+                    for history in self.inputs.iter_mut() {
+                        history.clear();
+                    }
+                }
+                inputs.blocks[0] = Block::default();
+                return inputs;
+            }
+        } else {
+            self.silent_blocks = 0;
+        }
+
+        let mut input = inputs.blocks[0].take();
+        // At most two input channels are convolved. True stereo processing
+        // of a mono input treats it as stereo, and the input never has fewer
+        // channels than the history, so that the tail of every channel
+        // still gets output.
+        let mut input_chans = (input.chan_count() as usize).max(self.inputs.len());
+        if input_chans > 2 || kernel_chans == 4 {
+            input_chans = 2;
+        }
+        if input.chan_count() as usize != input_chans {
+            input.mix(input_chans as u8, ChannelInterpretation::Speakers);
+        }
+
+        while self.inputs.len() < input_chans {
+            self.inputs.push(InputHistory::new(partitions));
+        }
+        for chan in 0..self.inputs.len() {
+            if chan < input_chans && !input.is_silence() {
+                self.push_input(chan, Some(input.data_chan(chan as u8)));
+            } else {
+                self.push_input(chan, None);
+            }
+        }
+
+        // https://webaudio.github.io/web-audio-api/#Convolution-channel-configurations
+        let output_chans = if kernel_chans == 1 { input_chans } else { 2 };
+        let mut output = Block::for_channels_explicit(output_chans as u8);
+        for out in 0..output_chans {
+            match (kernel_chans, input_chans) {
+                (4, _) => {
+                    // L = inL * IR[0] + inR * IR[1]
+                    // R = inL * IR[2] + inR * IR[3]
+                    self.accumulate(0, 2 * out);
+                    self.accumulate(1, 2 * out + 1);
+                }
+                (2, 1) => self.accumulate(0, out),
+                (2, _) => self.accumulate(out, out),
+                _ => self.accumulate(out, 0),
+            }
+            self.flush_accumulator(output.data_chan_mut(out as u8));
+        }
+
+        self.position = (self.position + 1) % partitions;
+        inputs.blocks[0] = output;
+        inputs
+    }
+
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        self.kernel
+            .as_ref()
+            .map_or(false, |kernel| self.silent_blocks <= kernel.partitions)
+    }
+
+    make_message_handler!(ConvolverNode: handle_message);
+}
@@ -0,0 +1,216 @@
+use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK_USIZE};
+use node::{AudioNodeEngine, BlockInfo};
+use node::{AudioNodeType, ChannelInfo, ChannelInterpretation};
+use param::{Param, ParamDescriptor, ParamType};
+
+#[derive(Copy, Clone, Debug)]
+pub struct DelayNodeOptions {
+    /// The maximum delay time, in seconds
+    pub max_delay_time: f64,
+    /// The initial value for the delay_time AudioParam, in seconds
+    pub delay_time: f32,
+}
+
+impl Default for DelayNodeOptions {
+    fn default() -> Self {
+        DelayNodeOptions {
+            max_delay_time: 1.,
+            delay_time: 0.,
+        }
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#DelayNode
+///
+/// Input is written into one ring buffer per channel, and the
+/// output is read back from it `delay_time` seconds behind, with linear
+/// interpolation for delays that aren't a whole number of frames.
+///
+/// A DelayNode which is part of a cycle is split in two by the graph:
+/// its output is read before the rest of the cycle runs, and its input
+/// is written once the rest of the cycle is done. In that case the delay
+/// is clamped to at least one render quantum.
+#[derive(AudioNodeCommon)]
+pub(crate) struct DelayNode {
+    channel_info: ChannelInfo,
+    delay_time: Param,
+    /// The maximum delay, in frames
+    max_delay_frames: f64,
+    /// One ring buffer per channel, all of length `buffer_len`
+    buffer: Vec<Vec<f32>>,
+    buffer_len: usize,
+    /// Where the next block of input will be written to
+    write_index: usize,
+    /// The number of silent frames written since the last non-silent
+    /// input. Once this reaches `buffer_len` the whole buffer is silent.
+    silent_frames: usize,
+}
+
+impl DelayNode {
+    pub fn new(options: DelayNodeOptions, channel_info: ChannelInfo, sample_rate: f32) -> Self {
+        debug_assert!(options.max_delay_time > 0. && options.max_delay_time < 180.);
+        let max_delay_frames = options.max_delay_time * sample_rate as f64;
+        // We need to keep around an extra block of history, since the
+        // block currently being written may be read from, as well as an
+        // extra frame for interpolation
+        let buffer_len = max_delay_frames.ceil() as usize + FRAMES_PER_BLOCK_USIZE + 2;
+        let silent_frames = buffer_len;
+        Self {
+            channel_info,
+            delay_time: Param::new(
+                options.delay_time,
+                ParamDescriptor::new(0., 0., options.max_delay_time as f32),
+            ),
+            max_delay_frames,
+            buffer: vec![vec![0.; buffer_len]],
+            buffer_len,
+            write_index: 0,
+            silent_frames,
+        }
+    }
+
+    /// Write a block of input into the ring buffers
+    fn write(&mut self, mut block: Block) {
+        if block.is_silence() {
+            if self.silent_frames >= self.buffer_len {
+                // The buffer only contains silence already
+                return;
+            }
+            self.silent_frames += FRAMES_PER_BLOCK_USIZE;
+        } else {
+            self.silent_frames = 0;
+        }
+
+        let chans = block.chan_count() as usize;
+        if chans > self.buffer.len() {
+            let buffer_len = self.buffer_len;
+            self.buffer.resize(chans, vec![0.; buffer_len]);
+        } else if chans < self.buffer.len() {
+            block.mix(self.buffer.len() as u8, ChannelInterpretation::Speakers);
+        }
+
+        let start = self.write_index;
+        let first = (self.buffer_len - start).min(FRAMES_PER_BLOCK_USIZE);
+        for (chan, buf) in self.buffer.iter_mut().enumerate() {
+            if block.is_silence() {
+                for sample in buf[start..start + first].iter_mut() {
+                    *sample = 0.;
+                }
+                for sample in buf[..FRAMES_PER_BLOCK_USIZE - first].iter_mut() {
+                    *sample = 0.;
+                }
+            } else {
+                let data = block.data_chan(chan as u8);
+                buf[start..start + first].copy_from_slice(&data[..first]);
+                buf[..FRAMES_PER_BLOCK_USIZE - first].copy_from_slice(&data[first..]);
+            }
+        }
+
+        if self.silent_frames >= self.buffer_len {
+            // Nothing left in the buffer, so the output is free to
+            // change its channel count
+            self.buffer.truncate(1);
+        }
+    }
+
+    /// Move on to the next block
+    fn advance(&mut self) {
+        self.write_index = (self.write_index + FRAMES_PER_BLOCK_USIZE) % self.buffer_len;
+    }
+
+    /// Read a block of output from the ring buffers, with the
+    /// delay clamped to at least `min_delay` frames
+    fn read(&mut self, info: &BlockInfo, min_delay: f64) -> Block {
+        if self.silent_frames >= self.buffer_len {
+            // The param must still see every tick
+            for tick in 0..FRAMES_PER_BLOCK_USIZE {
+                self.delay_time.update(info, Tick(tick as u64));
+            }
+            return Block::default();
+        }
+
+        let sample_rate = info.sample_rate as f64;
+        let max_delay = self.max_delay_frames;
+        let clamp = |delay: f32| {
+            let delay = delay as f64 * sample_rate;
+            if delay < min_delay {
+                min_delay
+            } else if delay > max_delay {
+                max_delay
+            } else {
+                delay
+            }
+        };
+
+        let len = self.buffer_len;
+        let mut block = Block::for_channels_explicit(self.buffer.len() as u8);
+        {
+            let data = block.data_mut();
+            let mut delay = clamp(self.delay_time.value());
+            for frame in 0..FRAMES_PER_BLOCK_USIZE {
+                if self.delay_time.update(info, Tick(frame as u64)) {
+                    delay = clamp(self.delay_time.value());
+                }
+                // `len` is added to avoid going negative, since
+                // delay < len
+                let position = (self.write_index + frame + len) as f64 - delay;
+                let floor = position.floor();
+                let fraction = (position - floor) as f32;
+                let index = floor as usize % len;
+                let next = (index + 1) % len;
+                for (chan, buf) in self.buffer.iter().enumerate() {
+                    data[chan * FRAMES_PER_BLOCK_USIZE + frame] =
+                        buf[index] + (buf[next] - buf[index]) * fraction;
+                }
+            }
+        }
+        block
+    }
+}
+
+impl AudioNodeEngine for DelayNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::DelayNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+
+        let input = inputs.blocks[0].take();
+        self.write(input);
+        inputs.blocks[0] = self.read(info, 0.);
+        self.advance();
+        inputs
+    }
+
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        self.silent_frames < self.buffer_len
+    }
+
+    fn breaks_cycles(&self) -> bool {
+        true
+    }
+
+    fn process_cycle_output(&mut self, info: &BlockInfo) -> Chunk {
+        let mut chunk = Chunk::default();
+        chunk
+            .blocks
+            .push(self.read(info, FRAMES_PER_BLOCK_USIZE as f64));
+        chunk
+    }
+
+    fn process_cycle_input(&mut self, mut inputs: Chunk, _: &BlockInfo) {
+        debug_assert!(inputs.len() == 1);
+
+        let input = inputs.blocks[0].take();
+        self.write(input);
+        self.advance();
+    }
+
+    fn get_param(&mut self, id: ParamType) -> &mut Param {
+        match id {
+            ParamType::DelayTime => &mut self.delay_time,
+            _ => panic!("Unknown param {:?} for DelayNode", id),
+        }
+    }
+}
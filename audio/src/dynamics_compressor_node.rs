@@ -0,0 +1,214 @@
+use block::{Chunk, Tick, FRAMES_PER_BLOCK_USIZE};
+use node::{AudioNodeEngine, BlockInfo};
+use node::{AudioNodeType, ChannelInfo};
+use param::{Param, ParamDescriptor, ParamType};
+use std::sync::mpsc::Sender;
+
+#[derive(Copy, Clone, Debug)]
+pub struct DynamicsCompressorNodeOptions {
+    /// The level above which compression starts, in dB
+    pub threshold: f32,
+    /// The range above the threshold over which the curve
+    /// smoothly transitions to the compressed portion, in dB
+    pub knee: f32,
+    /// The amount of dB change in input for a 1 dB change in output
+    pub ratio: f32,
+    /// How quickly the gain is reduced, in seconds
+    pub attack: f32,
+    /// How quickly the gain is increased back, in seconds
+    pub release: f32,
+}
+
+impl Default for DynamicsCompressorNodeOptions {
+    fn default() -> Self {
+        DynamicsCompressorNodeOptions {
+            threshold: -24.,
+            knee: 30.,
+            ratio: 12.,
+            attack: 0.003,
+            release: 0.25,
+        }
+    }
+}
+
+/// Type of message directed to DynamicsCompressorNodes.
+#[derive(Debug)]
+pub enum DynamicsCompressorNodeMessage {
+    /// Get the amount of gain reduction currently applied, in dB
+    GetReduction(Sender<f32>),
+}
+
+/// The compression curve, in dB
+///
+/// https://webaudio.github.io/web-audio-api/#compression-curve
+#[derive(Copy, Clone)]
+struct Curve {
+    threshold: f32,
+    knee: f32,
+    ratio: f32,
+}
+
+impl Curve {
+    fn apply(&self, db: f32) -> f32 {
+        let over = db - self.threshold;
+        if over <= 0. {
+            db
+        } else if over < self.knee {
+            // A quadratic joining the identity and the
+            // compressed line, with matching slopes
+            db + (1. / self.ratio - 1.) * over * over / (2. * self.knee)
+        } else {
+            self.threshold
+                + self.knee * (1. + 1. / self.ratio) / 2.
+                + (over - self.knee) / self.ratio
+        }
+    }
+}
+
+fn to_db(linear: f32) -> f32 {
+    20. * linear.log10()
+}
+
+fn to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
+
+/// https://webaudio.github.io/web-audio-api/#DynamicsCompressorNode
+///
+/// The detector follows the peak of all the input channels. The gain the
+/// curve asks for is approached with a one pole smoother, using the attack
+/// coefficient when the gain has to go down and the release coefficient
+/// when it goes back up. A makeup gain is then applied so that full scale
+/// input stays at roughly the same loudness.
+#[derive(AudioNodeCommon)]
+pub(crate) struct DynamicsCompressorNode {
+    channel_info: ChannelInfo,
+    threshold: Param,
+    knee: Param,
+    ratio: Param,
+    attack: Param,
+    release: Param,
+    /// The smoothed gain, without makeup, as a linear value
+    gain: f32,
+}
+
+impl DynamicsCompressorNode {
+    pub fn new(options: DynamicsCompressorNodeOptions, channel_info: ChannelInfo) -> Self {
+        let k_rate = |val, default, min, max| {
+            Param::new(val, ParamDescriptor::fixed_k_rate(default, min, max))
+        };
+        Self {
+            channel_info,
+            threshold: k_rate(options.threshold, -24., -100., 0.),
+            knee: k_rate(options.knee, 30., 0., 40.),
+            ratio: k_rate(options.ratio, 12., 1., 20.),
+            attack: k_rate(options.attack, 0.003, 0., 1.),
+            release: k_rate(options.release, 0.25, 0., 1.),
+            gain: 1.,
+        }
+    }
+
+    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
+        let mut changed = self.threshold.update(info, tick);
+        changed |= self.knee.update(info, tick);
+        changed |= self.ratio.update(info, tick);
+        changed |= self.attack.update(info, tick);
+        changed |= self.release.update(info, tick);
+        changed
+    }
+
+    /// The curve for the current param values
+    fn curve(&self) -> Curve {
+        Curve {
+            threshold: self.threshold.value(),
+            knee: self.knee.value(),
+            ratio: self.ratio.value(),
+        }
+    }
+
+    pub fn handle_message(&mut self, message: DynamicsCompressorNodeMessage, _: f32) {
+        match message {
+            DynamicsCompressorNodeMessage::GetReduction(tx) => {
+                let _ = tx.send(to_db(self.gain));
+            }
+        }
+    }
+}
+
+/// The coefficient of a one pole smoother with a time
+/// constant of `time` seconds
+fn smoothing_coefficient(time: f32, sample_rate: f32) -> f32 {
+    if time == 0. {
+        0.
+    } else {
+        // after `time` seconds, the remaining distance is
+        // e^-1 of the initial one
+        (-1. / (time * sample_rate)).exp()
+    }
+}
+
+impl AudioNodeEngine for DynamicsCompressorNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::DynamicsCompressionNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+
+        // All params are k-rate
+        self.update_parameters(info, Tick(0));
+        let attack = smoothing_coefficient(self.attack.value(), info.sample_rate);
+        let release = smoothing_coefficient(self.release.value(), info.sample_rate);
+
+        if inputs.blocks[0].is_silence() {
+            // The gain can only be released, which is enough to
+            // compute where it ends up at the end of the block
+            self.gain = 1. - (1. - self.gain) * release.powi(FRAMES_PER_BLOCK_USIZE as i32);
+            return inputs;
+        }
+
+        let curve = self.curve();
+        let makeup = to_linear(-0.6 * curve.apply(0.));
+
+        let mut gains = [0.; FRAMES_PER_BLOCK_USIZE];
+        {
+            let block = &inputs.blocks[0];
+            for (frame, gain) in gains.iter_mut().enumerate() {
+                let peak = (0..block.chan_count())
+                    .map(|chan| block.data_chan_frame(frame, chan).abs())
+                    .fold(0., f32::max);
+                let target = if peak > 0. {
+                    let db = to_db(peak);
+                    to_linear(curve.apply(db) - db)
+                } else {
+                    1.
+                };
+                let coefficient = if target < self.gain { attack } else { release };
+                self.gain = target + (self.gain - target) * coefficient;
+                *gain = self.gain * makeup;
+            }
+        }
+
+        {
+            let mut iter = inputs.blocks[0].iter();
+            while let Some(mut frame) = iter.next() {
+                let gain = gains[frame.tick().0 as usize];
+                frame.mutate_with(|sample, _| *sample *= gain);
+            }
+        }
+        inputs
+    }
+
+    fn get_param(&mut self, id: ParamType) -> &mut Param {
+        match id {
+            ParamType::Threshold => &mut self.threshold,
+            ParamType::Knee => &mut self.knee,
+            ParamType::Ratio => &mut self.ratio,
+            ParamType::Attack => &mut self.attack,
+            ParamType::Release => &mut self.release,
+            _ => panic!("Unknown param {:?} for DynamicsCompressorNode", id),
+        }
+    }
+
+    make_message_handler!(DynamicsCompressionNode: handle_message);
+}
@@ -0,0 +1,203 @@
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+/// A complex number
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn conj(self) -> Self {
+        Complex::new(self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, other: Complex) {
+        self.re += other.re;
+        self.im += other.im;
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Mul<f32> for Complex {
+    type Output = Complex;
+    fn mul(self, other: f32) -> Complex {
+        Complex::new(self.re * other, self.im * other)
+    }
+}
+
+/// An in-place radix-2 FFT for a fixed power-of-two size
+///
+/// All the twiddle factors and the bit reversal permutation are
+/// computed up front, so transforms don't allocate.
+pub struct Fft {
+    size: usize,
+    /// e^(-2πik/size) for k in 0..size/2
+    twiddles: Vec<Complex>,
+    /// The index each element is swapped with before the butterflies
+    bit_reverse: Vec<usize>,
+}
+
+impl Fft {
+    pub fn new(size: usize) -> Self {
+        // must be a power of two
+        debug_assert!(size >= 2 && size & (size - 1) == 0);
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let angle = -2. * PI * k as f64 / size as f64;
+                Complex::new(angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+        let bits = size.trailing_zeros();
+        let bit_reverse = (0..size)
+            .map(|i| {
+                (0..bits)
+                    .filter(|bit| i & (1 << bit) != 0)
+                    .fold(0, |acc, bit| acc | 1 << (bits - 1 - bit))
+            })
+            .collect();
+        Fft {
+            size,
+            twiddles,
+            bit_reverse,
+        }
+    }
+
+    /// Forward transform of `data`, in place
+    pub fn forward(&self, data: &mut [Complex]) {
+        self.transform(data, false);
+    }
+
+    /// Inverse transform of `data`, in place
+    ///
+    /// The result is scaled by 1/size, so that `inverse` undoes `forward`
+    pub fn inverse(&self, data: &mut [Complex]) {
+        self.transform(data, true);
+        let scale = 1. / self.size as f32;
+        for x in data.iter_mut() {
+            *x = *x * scale;
+        }
+    }
+
+    fn transform(&self, data: &mut [Complex], inverse: bool) {
+        debug_assert!(data.len() == self.size);
+        for (i, &j) in self.bit_reverse.iter().enumerate() {
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= self.size {
+            let half = len / 2;
+            let step = self.size / len;
+            for start in (0..self.size).step_by(len) {
+                for k in 0..half {
+                    let mut twiddle = self.twiddles[k * step];
+                    if inverse {
+                        twiddle = twiddle.conj();
+                    }
+                    let even = data[start + k];
+                    let odd = data[start + k + half] * twiddle;
+                    data[start + k] = even + odd;
+                    data[start + k + half] = even - odd;
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+/// A radix-2 FFT of real input for a fixed power-of-two size
+///
+/// The input is packed into a complex sequence of half the size, whose
+/// transform is then split into the spectrum of the even and odd samples.
+/// Like `Fft`, all the twiddle factors and scratch space are allocated up
+/// front.
+pub struct RealFft {
+    size: usize,
+    fft: Fft,
+    /// e^(-2πik/size) for k in 0..size/2
+    twiddles: Vec<Complex>,
+    scratch: Vec<Complex>,
+}
+
+impl RealFft {
+    pub fn new(size: usize) -> Self {
+        // must be a power of two
+        debug_assert!(size >= 4 && size & (size - 1) == 0);
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let angle = -2. * PI * k as f64 / size as f64;
+                Complex::new(angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+        RealFft {
+            size,
+            fft: Fft::new(size / 2),
+            twiddles,
+            scratch: vec![Complex::default(); size / 2],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Forward transform of `input`, of length `size`, into the first
+    /// `size / 2 + 1` bins of its spectrum, the rest being their conjugates
+    pub fn forward(&mut self, input: &[f32], output: &mut [Complex]) {
+        debug_assert!(input.len() == self.size);
+        debug_assert!(output.len() == self.size / 2 + 1);
+        let half = self.size / 2;
+        for (z, pair) in self.scratch.iter_mut().zip(input.chunks(2)) {
+            *z = Complex::new(pair[0], pair[1]);
+        }
+        self.fft.forward(&mut self.scratch);
+
+        for k in 0..half + 1 {
+            let z = self.scratch[k % half];
+            let z_mirror = self.scratch[(half - k) % half].conj();
+            // The spectra of the even and odd samples
+            let even = (z + z_mirror) * 0.5;
+            let diff = (z - z_mirror) * 0.5;
+            let odd = Complex::new(diff.im, -diff.re);
+            let twiddle = if k < half {
+                self.twiddles[k]
+            } else {
+                Complex::new(-1., 0.)
+            };
+            output[k] = even + odd * twiddle;
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use block::Chunk;
 use block::Tick;
+use block::FRAMES_PER_BLOCK_USIZE;
 use node::AudioNodeEngine;
 use node::BlockInfo;
 use node::{AudioNodeType, ChannelInfo};
-use param::{Param, ParamType};
+use param::{Param, ParamDescriptor, ParamType};
 
 #[derive(Copy, Clone, Debug)]
 pub struct GainNodeOptions {
@@ -26,12 +27,12 @@ impl GainNode {
     pub fn new(options: GainNodeOptions, channel_info: ChannelInfo) -> Self {
         Self {
             channel_info,
-            gain: Param::new(options.gain),
+            gain: Param::new(options.gain, ParamDescriptor::unbounded(1.)),
         }
     }
 
-    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
-        self.gain.update(info, tick)
+    pub fn update_parameters(&mut self, info: &BlockInfo) -> bool {
+        self.gain.update_block(info)
     }
 }
 
@@ -47,15 +48,21 @@ impl AudioNodeEngine for GainNode {
             return inputs;
         }
 
-        {
-            let mut iter = inputs.blocks[0].iter();
-            let mut gain = self.gain.value();
-
-            while let Some(mut frame) = iter.next() {
-                if self.update_parameters(info, frame.tick()) {
-                    gain = self.gain.value();
+        self.update_parameters(info);
+        let data = inputs.blocks[0].data_mut();
+        if self.gain.is_constant() {
+            let gain = self.gain.block_value(Tick(0));
+            if gain != 1. {
+                for sample in data.iter_mut() {
+                    *sample *= gain;
+                }
+            }
+        } else {
+            // a repeated block only has one channel of data
+            for chan in data.chunks_mut(FRAMES_PER_BLOCK_USIZE) {
+                for (sample, gain) in chan.iter_mut().zip(self.gain.block_values()) {
+                    *sample *= gain;
                 }
-                frame.mutate_with(|sample, _| *sample = *sample * gain);
             }
         }
         inputs
@@ -3,13 +3,15 @@ use destination_node::DestinationNode;
 use listener::AudioListenerNode;
 use node::{AudioNodeEngine, BlockInfo, ChannelCountMode, ChannelInterpretation};
 use param::ParamType;
+use petgraph::algo::tarjan_scc;
 use petgraph::graph::DefaultIx;
 use petgraph::stable_graph::NodeIndex;
-use petgraph::stable_graph::StableGraph;
-use petgraph::visit::{DfsPostOrder, EdgeRef, Reversed};
+use petgraph::stable_graph::{EdgeReference, StableGraph};
+use petgraph::visit::{EdgeFiltered, EdgeRef, VisitMap, Visitable};
 use petgraph::Direction;
 use smallvec::SmallVec;
 use std::cell::{RefCell, RefMut};
+use std::collections::HashSet;
 use std::{cmp, fmt, hash};
 
 #[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash, Debug)]
@@ -95,10 +97,27 @@ pub struct AudioGraph {
     graph: StableGraph<Node, Edge>,
     dest_id: NodeId,
     listener_id: NodeId,
+    /// The order in which nodes are processed
+    order: Vec<NodeIndex>,
+    /// The nodes in `order` which break a cycle, and need
+    /// their inputs fed to them after everything else is processed
+    cycle_breakers: Vec<NodeIndex>,
+    /// Set when the graph changes and `order` must be recomputed
+    order_dirty: bool,
+    /// The nodes the control thread doesn't hold on to anymore, to be
+    /// removed once they stop actively processing
+    released: Vec<NodeId>,
 }
 
 pub(crate) struct Node {
     node: RefCell<Box<AudioNodeEngine>>,
+    /// This is a DelayNode in a cycle, its output is produced
+    /// before the rest of the cycle and its input consumed after
+    breaks_cycle: bool,
+    /// This node is in a cycle without a DelayNode and outputs silence
+    ///
+    /// https://webaudio.github.io/web-audio-api/#DelayNode
+    muted: bool,
 }
 
 /// An edge in the graph
@@ -166,18 +185,61 @@ impl AudioGraph {
             graph,
             dest_id,
             listener_id,
+            order: Vec::new(),
+            cycle_breakers: Vec::new(),
+            order_dirty: true,
+            released: Vec::new(),
         }
     }
 
     /// Create a node, obtain its id
     pub(crate) fn add_node(&mut self, node: Box<AudioNodeEngine>) -> NodeId {
+        self.order_dirty = true;
         NodeId(self.graph.add_node(Node::new(node)))
     }
 
+    /// Remove a node from the graph, along with all of its connections
+    ///
+    /// The id of the node may be reused for nodes created afterwards.
+    /// The destination and the listener can't be removed.
+    pub fn remove_node(&mut self, node: NodeId) {
+        if node == self.dest_id || node == self.listener_id {
+            return;
+        }
+        if self.graph.remove_node(node.0).is_some() {
+            self.order_dirty = true;
+        }
+        self.released.retain(|&id| id != node);
+    }
+
+    /// Let go of a node the control thread doesn't hold on to anymore
+    ///
+    /// The node is removed once it has no inputs left and isn't going to
+    /// output anything anymore.
+    ///
+    /// https://webaudio.github.io/web-audio-api/#actively-processing
+    pub fn release_node(&mut self, node: NodeId) {
+        if node == self.dest_id || node == self.listener_id || !self.has_node(node) {
+            return;
+        }
+        if !self.released.contains(&node) {
+            self.released.push(node);
+        }
+    }
+
+    /// Whether a node is in the graph, i.e. wasn't removed
+    pub fn has_node(&self, node: NodeId) -> bool {
+        self.graph.contains_node(node.0)
+    }
+
     /// Connect an output port to an input port
     ///
     /// The edge goes *from* the output port *to* the input port, connecting two nodes
     pub fn add_edge(&mut self, out: PortId<OutputPort>, inp: PortId<InputPort>) {
+        if !self.has_node(out.node()) || !self.has_node(inp.node()) {
+            return;
+        }
+        self.order_dirty = true;
         let edge = self
             .graph
             .edges(out.node().0)
@@ -204,6 +266,10 @@ impl AudioGraph {
     ///
     /// https://webaudio.github.io/web-audio-api/#dom-audionode-disconnect
     pub fn disconnect_all_from(&mut self, node: NodeId) {
+        if !self.has_node(node) {
+            return;
+        }
+        self.order_dirty = true;
         let edges = self.graph.edges(node.0).map(|e| e.id()).collect::<Vec<_>>();
         for edge in edges {
             self.graph.remove_edge(edge);
@@ -214,6 +280,10 @@ impl AudioGraph {
     // ///
     // /// https://webaudio.github.io/web-audio-api/#dom-audionode-disconnect-output
     pub fn disconnect_output(&mut self, out: PortId<OutputPort>) {
+        if !self.has_node(out.node()) {
+            return;
+        }
+        self.order_dirty = true;
         let candidates: Vec<_> = self
             .graph
             .edges(out.node().0)
@@ -235,6 +305,10 @@ impl AudioGraph {
     ///
     /// https://webaudio.github.io/web-audio-api/#dom-audionode-disconnect-destinationnode
     pub fn disconnect_between(&mut self, from: NodeId, to: NodeId) {
+        if !self.has_node(from) {
+            return;
+        }
+        self.order_dirty = true;
         let edge = self
             .graph
             .edges(from.0)
@@ -249,6 +323,10 @@ impl AudioGraph {
     ///
     /// https://webaudio.github.io/web-audio-api/#dom-audionode-disconnect-destinationnode-output
     pub fn disconnect_output_between(&mut self, out: PortId<OutputPort>, to: NodeId) {
+        if !self.has_node(out.node()) {
+            return;
+        }
+        self.order_dirty = true;
         let edge = self
             .graph
             .edges(out.node().0)
@@ -272,6 +350,10 @@ impl AudioGraph {
     ///
     /// https://webaudio.github.io/web-audio-api/#dom-audionode-disconnect-destinationparam
     pub fn disconnect_to(&mut self, node: NodeId, inp: PortId<InputPort>) {
+        if !self.has_node(node) {
+            return;
+        }
+        self.order_dirty = true;
         let edge = self
             .graph
             .edges(node.0)
@@ -298,6 +380,10 @@ impl AudioGraph {
         out: PortId<OutputPort>,
         inp: PortId<InputPort>,
     ) {
+        if !self.has_node(out.node()) {
+            return;
+        }
+        self.order_dirty = true;
         let edge = self
             .graph
             .edges(out.node().0)
@@ -333,158 +419,343 @@ impl AudioGraph {
         self.listener_id
     }
 
-    /// For a given block, process all the data on this graph
-    pub fn process(&mut self, info: &BlockInfo) -> Chunk {
+    /// Recompute the order in which nodes are processed
+    ///
+    /// Cycles in the graph are only allowed if they contain a DelayNode.
+    /// The audio inputs of such DelayNodes are ignored while sorting, which
+    /// lets them act as sources for the rest of the cycle. Their inputs are
+    /// fed to them once everything else has been processed.
+    ///
+    /// Nodes in cycles without a DelayNode are muted.
+    ///
+    /// https://webaudio.github.io/web-audio-api/#rendering-loop
+    fn update_order(&mut self) {
+        self.order_dirty = false;
+
+        let indices: Vec<_> = self.graph.node_indices().collect();
+        for &ix in &indices {
+            self.graph[ix].breaks_cycle = false;
+            self.graph[ix].muted = false;
+        }
+
+        // Find all the DelayNodes that are part of a cycle
+        let mut breakers = HashSet::new();
+        for scc in tarjan_scc(&self.graph) {
+            if self.is_cycle(&scc, |_| true) {
+                for ix in scc {
+                    if self.graph[ix].node.borrow().breaks_cycles() {
+                        breakers.insert(ix);
+                    }
+                }
+            }
+        }
+
+        // Anything still in a cycle once their audio inputs are cut is muted
+        let sccs = {
+            let graph = &self.graph;
+            let filtered = EdgeFiltered::from_fn(graph, |e| !is_cut(&breakers, e));
+            tarjan_scc(&filtered)
+        };
+        for scc in sccs {
+            if self.is_cycle(&scc, |e| !is_cut(&breakers, e)) {
+                for ix in scc {
+                    self.graph[ix].muted = true;
+                }
+            }
+        }
+        for &ix in &breakers {
+            self.graph[ix].breaks_cycle = !self.graph[ix].muted;
+        }
+
         // DFS post order: Children are processed before their parent,
         // which is exactly what we need since the parent depends on the
         // children's output
         //
-        // This will only visit each node once
-        let reversed = Reversed(&self.graph);
-        let mut visit = DfsPostOrder::new(reversed, self.dest_id.0);
+        // This will only visit each node once. The inputs of cycle-breaking
+        // nodes are visited after everything else
+        self.order.clear();
+        self.cycle_breakers.clear();
+        let mut discovered = self.graph.visit_map();
+        let mut finished = self.graph.visit_map();
+        // Sinks other than the destination are processed as well, along
+        // with everything they depend on
+        let mut roots = vec![self.dest_id.0];
+        roots.extend(
+            indices
+                .iter()
+                .filter(|&&ix| self.graph[ix].node.borrow().is_sink()),
+        );
+        let mut stack = Vec::new();
+        while let Some(root) = roots.pop() {
+            stack.push(root);
+            while let Some(&ix) = stack.last() {
+                if discovered.visit(ix) {
+                    for edge in self.graph.edges_directed(ix, Direction::Incoming) {
+                        if is_cut(&breakers, edge) {
+                            roots.push(edge.source());
+                        } else if !discovered.is_visited(&edge.source()) {
+                            stack.push(edge.source());
+                        }
+                    }
+                } else {
+                    stack.pop();
+                    if finished.visit(ix) {
+                        self.order.push(ix);
+                        if self.graph[ix].breaks_cycle {
+                            self.cycle_breakers.push(ix);
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-        let mut blocks: SmallVec<[SmallVec<[Block; 1]>; 1]> = SmallVec::new();
-        let mut output_counts: SmallVec<[u32; 1]> = SmallVec::new();
+    /// Whether a strongly connected component forms a cycle, only
+    /// considering edges for which `include` is true
+    fn is_cycle<F>(&self, scc: &[NodeIndex], include: F) -> bool
+    where
+        F: Fn(EdgeReference<Edge>) -> bool,
+    {
+        scc.len() > 1 || self
+            .graph
+            .edges(scc[0])
+            .any(|e| e.target() == scc[0] && include(e))
+    }
+
+    /// For a given block, process all the data on this graph
+    pub fn process(&mut self, info: &BlockInfo) -> Chunk {
+        if self.order_dirty {
+            self.update_order();
+        }
 
-        while let Some(ix) = visit.next(reversed) {
+        for &ix in &self.order {
+            let node = &self.graph[ix];
+            let mut curr = node.node.borrow_mut();
+
+            let out = if node.muted {
+                self.discard_inputs(ix);
+                let mut chunk = Chunk::default();
+                chunk
+                    .blocks
+                    .resize(curr.output_count() as usize, Default::default());
+                chunk
+            } else if node.breaks_cycle {
+                // The audio inputs for this block aren't known yet, they
+                // are handled below
+                self.collect_inputs(ix, &mut **curr, false);
+                curr.process_cycle_output(info)
+            } else {
+                let chunk = self.collect_inputs(ix, &mut **curr, true);
+                curr.process(chunk, info)
+            };
+
+            self.distribute_outputs(ix, &**curr, out);
+        }
+
+        for &ix in &self.cycle_breakers {
             let mut curr = self.graph[ix].node.borrow_mut();
+            let chunk = self.collect_port_inputs(ix, &**curr);
+            curr.process_cycle_input(chunk, info);
+        }
 
-            let mut chunk = Chunk::default();
-            chunk
-                .blocks
-                .resize(curr.input_count() as usize, Default::default());
-
-            // if we have inputs, collect all the computed blocks
-            // and construct a Chunk
-
-            // set up scratch space to store all the blocks
-            blocks.clear();
-            blocks.resize(curr.input_count() as usize, Default::default());
-
-            let mode = curr.channel_count_mode();
-            let count = curr.channel_count();
-            let interpretation = curr.channel_interpretation();
-
-            // all edges to this node are from its dependencies
-            for edge in self.graph.edges_directed(ix, Direction::Incoming) {
-                let edge = edge.weight();
-                for connection in &edge.connections {
-                    let mut block = connection
-                        .cache
-                        .borrow_mut()
-                        .take()
-                        .expect("Cache should have been filled from traversal");
-
-                    match connection.input_idx {
-                        PortIndex::Port(idx) => {
-                            blocks[idx as usize].push(block);
-                        }
-                        PortIndex::Param(param) => {
-                            // param inputs are downmixed to mono
-                            // https://webaudio.github.io/web-audio-api/#dom-audionode-connect-destinationparam-output
-                            block.mix(1, ChannelInterpretation::Speakers);
-                            curr.get_param(param).add_block(block)
-                        }
-                        PortIndex::Listener(_) => curr.set_listenerdata(block),
+        if !self.released.is_empty() {
+            self.collect_released(info);
+        }
+
+        // The destination node stores its output on itself, extract it.
+        self.graph[self.dest_id.0]
+            .node
+            .borrow_mut()
+            .destination_data()
+            .expect("Destination node should have data cached")
+    }
+
+    /// Remove the released nodes which have no inputs left, and won't
+    /// output anything anymore after the block described by `info`
+    ///
+    /// Nodes which only had released nodes as inputs are removed in the
+    /// following blocks, once their own tail time has elapsed.
+    fn collect_released(&mut self, info: &BlockInfo) {
+        let mut i = 0;
+        while i < self.released.len() {
+            let ix = self.released[i].0;
+            if self.has_inputs(ix) || self.graph[ix].node.borrow().has_remaining_output(info) {
+                i += 1;
+            } else {
+                self.released.swap_remove(i);
+                self.graph.remove_node(ix);
+                self.order_dirty = true;
+            }
+        }
+    }
+
+    /// Whether anything is connected to the audio inputs or params of a
+    /// node, the connection from the listener aside
+    fn has_inputs(&self, ix: NodeIndex) -> bool {
+        self.graph
+            .edges_directed(ix, Direction::Incoming)
+            .any(|edge| {
+                edge.weight().connections.iter().any(|c| match c.input_idx {
+                    PortIndex::Listener(_) => false,
+                    _ => true,
+                })
+            })
+    }
+
+    /// Gather the inputs of a node into a Chunk
+    ///
+    /// Param and listener inputs are handed directly to the node. If `ports`
+    /// is false, the audio inputs are left alone and an empty Chunk is returned.
+    fn collect_inputs(&self, ix: NodeIndex, curr: &mut AudioNodeEngine, ports: bool) -> Chunk {
+        for edge in self.graph.edges_directed(ix, Direction::Incoming) {
+            let edge = edge.weight();
+            for connection in &edge.connections {
+                match connection.input_idx {
+                    PortIndex::Port(_) => (),
+                    PortIndex::Param(param) => {
+                        let mut block = connection.take_cache();
+                        // param inputs are downmixed to mono
+                        // https://webaudio.github.io/web-audio-api/#dom-audionode-connect-destinationparam-output
+                        block.mix(1, ChannelInterpretation::Speakers);
+                        curr.get_param(param).add_block(block)
                     }
+                    PortIndex::Listener(_) => curr.set_listenerdata(connection.take_cache()),
                 }
             }
+        }
 
-            for (i, mut blocks) in blocks.drain().enumerate() {
-                if blocks.len() == 0 {
-                    if mode == ChannelCountMode::Explicit {
-                        // It's silence, but mix it anyway
+        if ports {
+            self.collect_port_inputs(ix, curr)
+        } else {
+            Chunk::default()
+        }
+    }
+
+    /// Gather and mix the audio inputs of a node into a Chunk
+    fn collect_port_inputs(&self, ix: NodeIndex, curr: &AudioNodeEngine) -> Chunk {
+        let mut chunk = Chunk::default();
+        chunk
+            .blocks
+            .resize(curr.input_count() as usize, Default::default());
+
+        // set up scratch space to store all the blocks
+        let mut blocks: SmallVec<[SmallVec<[Block; 1]>; 1]> = SmallVec::new();
+        blocks.resize(curr.input_count() as usize, Default::default());
+
+        let mode = curr.channel_count_mode();
+        let count = curr.channel_count();
+        let interpretation = curr.channel_interpretation();
+
+        // all edges to this node are from its dependencies
+        for edge in self.graph.edges_directed(ix, Direction::Incoming) {
+            let edge = edge.weight();
+            for connection in &edge.connections {
+                if let PortIndex::Port(idx) = connection.input_idx {
+                    blocks[idx as usize].push(connection.take_cache());
+                }
+            }
+        }
+
+        for (i, mut blocks) in blocks.drain().enumerate() {
+            if blocks.len() == 0 {
+                if mode == ChannelCountMode::Explicit {
+                    // It's silence, but mix it anyway
+                    chunk.blocks[i].mix(count, interpretation);
+                }
+            } else if blocks.len() == 1 {
+                chunk.blocks[i] = blocks.pop().expect("`blocks` had length 1");
+                match mode {
+                    ChannelCountMode::Explicit => {
                         chunk.blocks[i].mix(count, interpretation);
                     }
-                } else if blocks.len() == 1 {
-                    chunk.blocks[i] = blocks.pop().expect("`blocks` had length 1");
-                    match mode {
-                        ChannelCountMode::Explicit => {
+                    ChannelCountMode::ClampedMax => {
+                        if chunk.blocks[i].chan_count() > count {
                             chunk.blocks[i].mix(count, interpretation);
                         }
-                        ChannelCountMode::ClampedMax => {
-                            if chunk.blocks[i].chan_count() > count {
-                                chunk.blocks[i].mix(count, interpretation);
-                            }
-                        }
-                        // It's one channel, it maxes itself
-                        ChannelCountMode::Max => (),
                     }
-                } else {
-                    let mix_count = match mode {
-                        ChannelCountMode::Explicit => count,
-                        _ => {
-                            let mut max = 0; // max channel count
-                            for block in &blocks {
-                                max = cmp::max(max, block.chan_count());
-                            }
-                            if mode == ChannelCountMode::ClampedMax {
-                                max = cmp::min(max, count);
-                            }
-                            max
-                        }
-                    };
-                    let block = blocks.into_iter().fold(Block::default(), |acc, mut block| {
-                        block.mix(mix_count, interpretation);
-                        acc.sum(block)
-                    });
-                    chunk.blocks[i] = block;
+                    // It's one channel, it maxes itself
+                    ChannelCountMode::Max => (),
                 }
+            } else {
+                let mix_count = match mode {
+                    ChannelCountMode::Explicit => count,
+                    _ => {
+                        let mut max = 0; // max channel count
+                        for block in &blocks {
+                            max = cmp::max(max, block.chan_count());
+                        }
+                        if mode == ChannelCountMode::ClampedMax {
+                            max = cmp::min(max, count);
+                        }
+                        max
+                    }
+                };
+                let block = blocks.into_iter().fold(Block::default(), |acc, mut block| {
+                    block.mix(mix_count, interpretation);
+                    acc.sum(block)
+                });
+                chunk.blocks[i] = block;
             }
+        }
 
-            // actually run the node engine
-            let mut out = curr.process(chunk, info);
+        chunk
+    }
 
-            assert_eq!(out.len(), curr.output_count() as usize);
-            if curr.output_count() == 0 {
-                continue;
+    /// Throw away the inputs of a muted node
+    ///
+    /// Some of them may not have been computed, since the node is part of a cycle
+    fn discard_inputs(&self, ix: NodeIndex) {
+        for edge in self.graph.edges_directed(ix, Direction::Incoming) {
+            for connection in &edge.weight().connections {
+                connection.cache.borrow_mut().take();
             }
+        }
+    }
 
-            // Count how many output connections fan out from each port
-            // This is so that we don't have to needlessly clone audio buffers
-            //
-            // If this is inefficient, we can instead maintain this data
-            // cached on the node
-            output_counts.clear();
-            output_counts.resize(curr.output_count() as usize, 0);
-            for edge in self.graph.edges(ix) {
-                let edge = edge.weight();
-                for conn in &edge.connections {
-                    if let PortIndex::Port(idx) = conn.output_idx {
-                        output_counts[idx as usize] += 1;
-                    } else {
-                        unreachable!()
-                    }
+    /// Store the outputs of a node for retrieval by the nodes it outputs to
+    fn distribute_outputs(&self, ix: NodeIndex, curr: &AudioNodeEngine, mut out: Chunk) {
+        assert_eq!(out.len(), curr.output_count() as usize);
+        if curr.output_count() == 0 {
+            return;
+        }
+
+        // Count how many output connections fan out from each port
+        // This is so that we don't have to needlessly clone audio buffers
+        //
+        // If this is inefficient, we can instead maintain this data
+        // cached on the node
+        let mut output_counts: SmallVec<[u32; 1]> = SmallVec::new();
+        output_counts.resize(curr.output_count() as usize, 0);
+        for edge in self.graph.edges(ix) {
+            let edge = edge.weight();
+            for conn in &edge.connections {
+                if let PortIndex::Port(idx) = conn.output_idx {
+                    output_counts[idx as usize] += 1;
+                } else {
+                    unreachable!()
                 }
             }
+        }
 
-            // all the edges from this node go to nodes which depend on it,
-            // i.e. the nodes it outputs to. Store the blocks for retrieval.
-            for edge in self.graph.edges(ix) {
-                let edge = edge.weight();
-                for conn in &edge.connections {
-                    if let PortIndex::Port(idx) = conn.output_idx {
-                        output_counts[idx as usize] -= 1;
-                        // if there are no consumers left after this, take the data
-                        let block = if output_counts[idx as usize] == 0 {
-                            out[conn.output_idx].take()
-                        } else {
-                            out[conn.output_idx].clone()
-                        };
-                        *conn.cache.borrow_mut() = Some(block);
+        // all the edges from this node go to nodes which depend on it,
+        // i.e. the nodes it outputs to. Store the blocks for retrieval.
+        for edge in self.graph.edges(ix) {
+            let edge = edge.weight();
+            for conn in &edge.connections {
+                if let PortIndex::Port(idx) = conn.output_idx {
+                    output_counts[idx as usize] -= 1;
+                    // if there are no consumers left after this, take the data
+                    let block = if output_counts[idx as usize] == 0 {
+                        out[conn.output_idx].take()
                     } else {
-                        unreachable!()
-                    }
+                        out[conn.output_idx].clone()
+                    };
+                    *conn.cache.borrow_mut() = Some(block);
+                } else {
+                    unreachable!()
                 }
             }
         }
-
-        // The destination node stores its output on itself, extract it.
-        self.graph[self.dest_id.0]
-            .node
-            .borrow_mut()
-            .destination_data()
-            .expect("Destination node should have data cached")
     }
 
     /// Obtain a mutable reference to a node
@@ -497,6 +768,8 @@ impl Node {
     pub fn new(node: Box<AudioNodeEngine>) -> Self {
         Node {
             node: RefCell::new(node),
+            breaks_cycle: false,
+            muted: false,
         }
     }
 }
@@ -517,4 +790,24 @@ impl Connection {
             cache: RefCell::new(None),
         }
     }
+
+    fn take_cache(&self) -> Block {
+        self.cache
+            .borrow_mut()
+            .take()
+            .expect("Cache should have been filled from traversal")
+    }
+}
+
+/// Whether an edge is an audio input to a cycle-breaking node, these
+/// are ignored when ordering the graph
+fn is_cut(breakers: &HashSet<NodeIndex>, edge: EdgeReference<Edge>) -> bool {
+    breakers.contains(&edge.target()) && edge
+        .weight()
+        .connections
+        .iter()
+        .all(|c| match c.input_idx {
+            PortIndex::Port(_) => true,
+            _ => false,
+        })
 }
@@ -0,0 +1,383 @@
+use block::FRAMES_PER_BLOCK_USIZE;
+use fft::{Complex, Fft};
+use std::f32::consts::PI;
+use std::mem;
+use std::sync::Arc;
+
+/// Length of the impulse responses, short enough for a block to be
+/// convolved with them in a single FFT of twice the block size
+const KERNEL_LEN: usize = FRAMES_PER_BLOCK_USIZE;
+const FFT_SIZE: usize = 2 * FRAMES_PER_BLOCK_USIZE;
+
+/// The directions of the database, in degrees. Azimuths go all the way
+/// around, elevations from below the horizon up to the zenith.
+const AZIMUTH_STEP: f32 = 15.;
+const AZIMUTH_COUNT: usize = 24;
+const ELEVATION_STEP: f32 = 15.;
+const ELEVATION_MIN: f32 = -45.;
+const ELEVATION_COUNT: usize = 10;
+
+/// Radius of the head in meters, and speed of sound in meters per second
+const HEAD_RADIUS: f32 = 0.0875;
+const SPEED_OF_SOUND: f32 = 343.;
+
+/// The pinna echoes of the model: reflection coefficient, and the A, B
+/// and D parameters of their delay, A and B being in samples at 44.1kHz
+const PINNA_ECHOES: [(f32, f32, f32, f32); 5] = [
+    (0.5, 1., 2., 1.),
+    (-1., 5., 4., 0.5),
+    (0.5, 5., 7., 0.5),
+    (-0.25, 5., 11., 0.5),
+    (0.25, 5., 13., 0.5),
+];
+
+/// Longest delay a DelayLine supports, in frames, which is much more than
+/// the interaural delay even at high sample rates
+const MAX_DELAY: usize = 256;
+
+/// How long the output of an HrtfPanner lasts after its input goes silent,
+/// in frames
+pub(crate) const TAIL_FRAMES: usize = KERNEL_LEN + MAX_DELAY;
+
+/// The head-related impulse responses for a direction, for the left and
+/// right ears
+///
+/// The delay of each ear is kept apart from its kernel, so that
+/// responses can be interpolated without comb filtering.
+struct Hrir {
+    kernels: [Vec<f32>; 2],
+    /// In frames
+    delays: [f32; 2],
+}
+
+impl Hrir {
+    fn silent() -> Self {
+        Hrir {
+            kernels: [vec![0.; KERNEL_LEN], vec![0.; KERNEL_LEN]],
+            delays: [0.; 2],
+        }
+    }
+}
+
+/// The response of one ear, on the given side (-1 for the left ear, 1 for
+/// the right one), to a source in the given direction, in degrees
+///
+/// This follows the structural model of Brown and Duda, "A Structural
+/// Model for Binaural Sound Synthesis" (1998): a spherical head shadow
+/// filter with a delay for the path around the head, and pinna echoes
+/// which depend on the elevation.
+fn ear_response(side: f32, azimuth: f32, elevation: f32, sample_rate: f32) -> (Vec<f32>, f32) {
+    let (az, el) = (azimuth.to_radians(), elevation.to_radians());
+    // Angle between the axis of the ear and the source
+    let incidence = (side * el.cos() * az.sin()).max(-1.).min(1.).acos();
+
+    let radius_time = HEAD_RADIUS / SPEED_OF_SOUND;
+    let delay = if incidence < PI / 2. {
+        radius_time * (1. - incidence.cos())
+    } else {
+        radius_time * (1. + incidence - PI / 2.)
+    };
+
+    // The model only describes the pinna for sources in front,
+    // sources behind are mirrored
+    let front_azimuth = if azimuth > 90. {
+        180. - azimuth
+    } else if azimuth < -90. {
+        -180. - azimuth
+    } else {
+        azimuth
+    };
+    let mut pinna = vec![0.; KERNEL_LEN];
+    pinna[0] = 1.;
+    for &(reflection, a, b, d) in PINNA_ECHOES.iter() {
+        let echo_delay = a
+            * (front_azimuth / 2.).to_radians().cos()
+            * (d * (90. - elevation)).to_radians().sin()
+            + b;
+        let position = echo_delay * sample_rate / 44100.;
+        let index = position.floor();
+        let fraction = position - index;
+        let index = index as usize;
+        if index + 1 < KERNEL_LEN {
+            pinna[index] += reflection * (1. - fraction);
+            pinna[index + 1] += reflection * fraction;
+        }
+    }
+
+    // The head shadow, H(s) = (αs + β) / (s + β), through the bilinear
+    // transform
+    let alpha_min = 0.1;
+    let theta_min = 150f32.to_radians();
+    let alpha = 1. + alpha_min / 2. + (1. - alpha_min / 2.) * (incidence / theta_min * PI).cos();
+    let beta = 2. * SPEED_OF_SOUND / HEAD_RADIUS;
+    let k = 2. * sample_rate;
+    let a0 = k + beta;
+    let b0 = (alpha * k + beta) / a0;
+    let b1 = (beta - alpha * k) / a0;
+    let a1 = (beta - k) / a0;
+    let (mut x1, mut y1) = (0., 0.);
+    let kernel = pinna
+        .iter()
+        .map(|&x| {
+            let y = b0 * x + b1 * x1 - a1 * y1;
+            x1 = x;
+            y1 = y;
+            y
+        })
+        .collect();
+
+    (kernel, delay * sample_rate)
+}
+
+/// Head-related impulse responses on a grid of directions
+///
+/// The responses are synthesized from a model of the head for the
+/// sample rate of the context, rather than measured and resampled. They
+/// are computed once by the rendering thread and shared by all of its
+/// panners.
+pub(crate) struct HrtfDatabase {
+    /// Indexed by elevation, then azimuth
+    hrirs: Vec<Hrir>,
+}
+
+impl HrtfDatabase {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut hrirs = Vec::with_capacity(ELEVATION_COUNT * AZIMUTH_COUNT);
+        for e in 0..ELEVATION_COUNT {
+            let elevation = ELEVATION_MIN + e as f32 * ELEVATION_STEP;
+            for a in 0..AZIMUTH_COUNT {
+                let azimuth = a as f32 * AZIMUTH_STEP;
+                let azimuth = if azimuth > 180. {
+                    azimuth - 360.
+                } else {
+                    azimuth
+                };
+                let (left, left_delay) = ear_response(-1., azimuth, elevation, sample_rate);
+                let (right, right_delay) = ear_response(1., azimuth, elevation, sample_rate);
+                hrirs.push(Hrir {
+                    kernels: [left, right],
+                    delays: [left_delay, right_delay],
+                });
+            }
+        }
+        HrtfDatabase { hrirs }
+    }
+
+    /// The responses for a direction in degrees, interpolated between
+    /// the four closest ones of the grid
+    fn interpolate(&self, azimuth: f32, elevation: f32, hrir: &mut Hrir) {
+        let mut azimuth = azimuth % 360.;
+        if azimuth < 0. {
+            azimuth += 360.;
+        }
+        let position = azimuth / AZIMUTH_STEP;
+        let a0 = (position.floor() as usize) % AZIMUTH_COUNT;
+        let a1 = (a0 + 1) % AZIMUTH_COUNT;
+        let fa = position - position.floor();
+
+        let max = (ELEVATION_COUNT - 1) as f32;
+        let position = ((elevation - ELEVATION_MIN) / ELEVATION_STEP)
+            .max(0.)
+            .min(max);
+        let e0 = (position.floor() as usize).min(ELEVATION_COUNT - 2);
+        let fe = position - e0 as f32;
+
+        let corners = [
+            (e0, a0, (1. - fe) * (1. - fa)),
+            (e0, a1, (1. - fe) * fa),
+            (e0 + 1, a0, fe * (1. - fa)),
+            (e0 + 1, a1, fe * fa),
+        ];
+        for ear in 0..2 {
+            for x in hrir.kernels[ear].iter_mut() {
+                *x = 0.;
+            }
+            hrir.delays[ear] = 0.;
+            for &(e, a, weight) in corners.iter() {
+                let corner = &self.hrirs[e * AZIMUTH_COUNT + a];
+                for (x, &c) in hrir.kernels[ear].iter_mut().zip(&corner.kernels[ear]) {
+                    *x += weight * c;
+                }
+                hrir.delays[ear] += weight * corner.delays[ear];
+            }
+        }
+    }
+}
+
+/// A delay line with a fractional delay, which can change over a block
+struct DelayLine {
+    buffer: Vec<f32>,
+    write: usize,
+}
+
+impl DelayLine {
+    fn new() -> Self {
+        DelayLine {
+            buffer: vec![0.; MAX_DELAY],
+            write: 0,
+        }
+    }
+
+    /// Delay `data` in place, the delay going linearly from `from` to `to`
+    /// frames over it
+    fn process(&mut self, data: &mut [f32], from: f32, to: f32) {
+        let len = data.len() as f32;
+        for (n, sample) in data.iter_mut().enumerate() {
+            self.buffer[self.write] = *sample;
+            let delay = from + (to - from) * (n + 1) as f32 / len;
+            let position = self.write as f32 + MAX_DELAY as f32 - delay;
+            let index = position.floor();
+            let fraction = position - index;
+            let index = index as usize % MAX_DELAY;
+            let next = (index + 1) % MAX_DELAY;
+            *sample = self.buffer[index] * (1. - fraction) + self.buffer[next] * fraction;
+            self.write = (self.write + 1) % MAX_DELAY;
+        }
+    }
+}
+
+/// Binaural rendering of a source by convolution with head-related
+/// impulse responses
+///
+/// https://webaudio.github.io/web-audio-api/#Spatialization-hrtf-panning
+pub(crate) struct HrtfPanner {
+    database: Arc<HrtfDatabase>,
+    fft: Fft,
+    /// The direction the current responses are for
+    direction: Option<(f32, f32)>,
+    /// The spectra of the current kernels, for each ear
+    spectra: [Vec<Complex>; 2],
+    /// The spectra of the kernels being faded out, for each ear
+    previous_spectra: [Vec<Complex>; 2],
+    /// The current delays, for each ear
+    delays: [f32; 2],
+    /// The input of the previous block followed by the current one,
+    /// for each ear
+    inputs: [Vec<f32>; 2],
+    delay_lines: [DelayLine; 2],
+    hrir: Hrir,
+    input_spectrum: Vec<Complex>,
+    scratch: Vec<Complex>,
+    faded_out: Vec<f32>,
+}
+
+impl HrtfPanner {
+    pub fn new(database: Arc<HrtfDatabase>) -> Self {
+        HrtfPanner {
+            database,
+            fft: Fft::new(FFT_SIZE),
+            direction: None,
+            spectra: [
+                vec![Complex::default(); FFT_SIZE],
+                vec![Complex::default(); FFT_SIZE],
+            ],
+            previous_spectra: [
+                vec![Complex::default(); FFT_SIZE],
+                vec![Complex::default(); FFT_SIZE],
+            ],
+            delays: [0.; 2],
+            inputs: [vec![0.; FFT_SIZE], vec![0.; FFT_SIZE]],
+            delay_lines: [DelayLine::new(), DelayLine::new()],
+            hrir: Hrir::silent(),
+            input_spectrum: vec![Complex::default(); FFT_SIZE],
+            scratch: vec![Complex::default(); FFT_SIZE],
+            faded_out: vec![0.; FRAMES_PER_BLOCK_USIZE],
+        }
+    }
+
+    /// Pan a block for a source in the given direction, in degrees
+    ///
+    /// For mono input, `l` is the input and `r` is ignored, otherwise each
+    /// channel is heard by the ear on its side. When the direction
+    /// changes, the output is crossfaded from the previous responses to
+    /// avoid clicks.
+    pub fn pan(&mut self, azimuth: f32, elevation: f32, mono: bool, l: &mut [f32], r: &mut [f32]) {
+        let previous_direction = self.direction;
+        let changed = previous_direction != Some((azimuth, elevation));
+        let previous_delays = self.delays;
+        if changed {
+            self.direction = Some((azimuth, elevation));
+            mem::swap(&mut self.previous_spectra, &mut self.spectra);
+            self.database
+                .interpolate(azimuth, elevation, &mut self.hrir);
+            for ear in 0..2 {
+                let spectrum = &mut self.spectra[ear];
+                for x in spectrum.iter_mut() {
+                    *x = Complex::default();
+                }
+                for (x, &k) in spectrum.iter_mut().zip(&self.hrir.kernels[ear]) {
+                    *x = Complex::new(k, 0.);
+                }
+                self.fft.forward(spectrum);
+            }
+            self.delays = self.hrir.delays;
+        }
+        // Fade from the previous responses, unless there were none
+        let fade = changed && previous_direction.is_some();
+        let delays_from = if previous_direction.is_some() {
+            previous_delays
+        } else {
+            self.delays
+        };
+
+        // The output is written over the input, keep it around
+        for ear in 0..2 {
+            let input: &[f32] = if ear == 0 || mono { l } else { r };
+            let (history, current) = self.inputs[ear].split_at_mut(FRAMES_PER_BLOCK_USIZE);
+            history.copy_from_slice(current);
+            current.copy_from_slice(input);
+        }
+
+        for ear in 0..2 {
+            let output: &mut [f32] = if ear == 0 { &mut *l } else { &mut *r };
+            for (x, &sample) in self.input_spectrum.iter_mut().zip(&self.inputs[ear]) {
+                *x = Complex::new(sample, 0.);
+            }
+            self.fft.forward(&mut self.input_spectrum);
+
+            // Overlap-save: only the second half of the circular
+            // convolution is free of wrapped around samples
+            convolve(
+                &self.fft,
+                &self.input_spectrum,
+                &self.spectra[ear],
+                &mut self.scratch,
+                output,
+            );
+            if fade {
+                convolve(
+                    &self.fft,
+                    &self.input_spectrum,
+                    &self.previous_spectra[ear],
+                    &mut self.scratch,
+                    &mut self.faded_out,
+                );
+                let len = output.len() as f32;
+                for (n, (out, &old)) in output.iter_mut().zip(&self.faded_out).enumerate() {
+                    let gain = (n + 1) as f32 / len;
+                    *out = old + (*out - old) * gain;
+                }
+            }
+
+            self.delay_lines[ear].process(output, delays_from[ear], self.delays[ear]);
+        }
+    }
+}
+
+/// Multiply the input spectrum with a kernel spectrum, and write the
+/// valid part of the result to `output`
+fn convolve(
+    fft: &Fft,
+    input: &[Complex],
+    kernel: &[Complex],
+    scratch: &mut [Complex],
+    output: &mut [f32],
+) {
+    for ((x, &i), &k) in scratch.iter_mut().zip(input).zip(kernel) {
+        *x = i * k;
+    }
+    fft.inverse(scratch);
+    for (out, x) in output.iter_mut().zip(&scratch[FRAMES_PER_BLOCK_USIZE..]) {
+        *out = x.re;
+    }
+}
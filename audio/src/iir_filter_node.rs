@@ -0,0 +1,241 @@
+use block::Chunk;
+use node::{AudioNodeEngine, AudioNodeError, BlockInfo};
+use node::{AudioNodeType, ChannelInfo};
+use smallvec::SmallVec;
+use std::f32;
+use std::f64::consts::PI;
+use std::sync::mpsc::Sender;
+
+/// The maximum number of coefficients of each kind
+///
+/// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createiirfilter
+pub const MAX_COEFFICIENTS: usize = 20;
+
+#[derive(Clone, Debug)]
+pub struct IIRFilterNodeOptions {
+    /// The numerator coefficients of the transfer function,
+    /// at least one of which must be nonzero
+    pub feedforward: Vec<f64>,
+    /// The denominator coefficients of the transfer function,
+    /// the first of which must be nonzero
+    pub feedback: Vec<f64>,
+}
+
+impl IIRFilterNodeOptions {
+    /// https://webaudio.github.io/web-audio-api/#dom-baseaudiocontext-createiirfilter
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        let valid_len = |coefficients: &[f64]| {
+            !coefficients.is_empty() && coefficients.len() <= MAX_COEFFICIENTS
+        };
+        if !valid_len(&self.feedforward) || !valid_len(&self.feedback) {
+            return Err(AudioNodeError::NotSupported);
+        }
+        if self.feedforward.iter().all(|&b| b == 0.) || self.feedback[0] == 0. {
+            return Err(AudioNodeError::InvalidState);
+        }
+        Ok(())
+    }
+}
+
+/// Type of message directed to IIRFilterNodes.
+#[derive(Debug)]
+pub enum IIRFilterNodeMessage {
+    /// Compute the magnitude and phase response of the filter at each of
+    /// the given frequencies, in Hz. Frequencies outside of [0, nyquist]
+    /// get NaN for both.
+    GetFrequencyResponse(Vec<f32>, Sender<(Vec<f32>, Vec<f32>)>),
+}
+
+/// The previous input and output values, per-channel
+#[derive(Clone, PartialEq)]
+struct IIRFilterState {
+    /// Input values, `x[i]` is the input from `i + 1` frames ago
+    x: [f64; MAX_COEFFICIENTS],
+    /// Output values, `y[i]` is the output from `i + 1` frames ago
+    y: [f64; MAX_COEFFICIENTS],
+}
+
+impl Default for IIRFilterState {
+    fn default() -> Self {
+        IIRFilterState {
+            x: [0.; MAX_COEFFICIENTS],
+            y: [0.; MAX_COEFFICIENTS],
+        }
+    }
+}
+
+impl IIRFilterState {
+    /// Update with new input/output values from this frame
+    fn update(&mut self, x: f64, y: f64) {
+        for i in (1..MAX_COEFFICIENTS).rev() {
+            self.x[i] = self.x[i - 1];
+            self.y[i] = self.y[i - 1];
+        }
+        self.x[0] = x;
+        self.y[0] = y;
+    }
+
+    /// Whether the filter has settled, with anything left of the previous
+    /// input being too small to be heard
+    fn is_quiescent(&self) -> bool {
+        self.x
+            .iter()
+            .chain(self.y.iter())
+            .all(|v| v.abs() < f32::MIN_POSITIVE as f64)
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#iirfilternode
+#[derive(AudioNodeCommon)]
+pub(crate) struct IIRFilterNode {
+    channel_info: ChannelInfo,
+    /// The feedforward coefficients b_k
+    /// These are actually b_k / a_0, we pre-divide
+    /// for efficiency
+    feedforward: Vec<f64>,
+    /// The feedback coefficients a_k
+    /// These are actually a_k / a_0, we pre-divide
+    /// for efficiency
+    feedback: Vec<f64>,
+    /// Stored filter state, this contains the previous
+    /// frames of input and output values for every
+    /// channel
+    state: SmallVec<[IIRFilterState; 2]>,
+}
+
+impl IIRFilterNode {
+    pub fn new(options: IIRFilterNodeOptions, channel_info: ChannelInfo) -> Self {
+        debug_assert!(options.validate().is_ok());
+        let IIRFilterNodeOptions {
+            mut feedforward,
+            mut feedback,
+        } = options;
+        let a0 = feedback[0];
+        for b in feedforward.iter_mut() {
+            *b /= a0;
+        }
+        for a in feedback.iter_mut() {
+            *a /= a0;
+        }
+        Self {
+            channel_info,
+            feedforward,
+            feedback,
+            state: SmallVec::new(),
+        }
+    }
+
+    pub fn handle_message(&mut self, message: IIRFilterNodeMessage, sample_rate: f32) {
+        match message {
+            IIRFilterNodeMessage::GetFrequencyResponse(frequencies, tx) => {
+                let _ = tx.send(frequency_response(
+                    &self.feedforward,
+                    &self.feedback,
+                    &frequencies,
+                    sample_rate,
+                ));
+            }
+        }
+    }
+}
+
+/// Evaluate the transfer function with the given numerator and denominator
+/// coefficients on the unit circle, returning the magnitude and phase
+/// response at each of the given frequencies
+///
+/// https://webaudio.github.io/web-audio-api/#dom-iirfilternode-getfrequencyresponse
+pub(crate) fn frequency_response(
+    feedforward: &[f64],
+    feedback: &[f64],
+    frequencies: &[f32],
+    sample_rate: f32,
+) -> (Vec<f32>, Vec<f32>) {
+    let nyquist = sample_rate / 2.;
+    frequencies
+        .iter()
+        .map(|&freq| {
+            if !(freq >= 0. && freq <= nyquist) {
+                return (::std::f32::NAN, ::std::f32::NAN);
+            }
+            let omega = PI * freq as f64 / nyquist as f64;
+            // sum of c_k * z^-k, for z = e^(iω)
+            let evaluate = |coefficients: &[f64]| {
+                coefficients
+                    .iter()
+                    .enumerate()
+                    .fold((0., 0.), |(re, im), (k, c)| {
+                        let angle = -omega * k as f64;
+                        (re + c * angle.cos(), im + c * angle.sin())
+                    })
+            };
+            let (num_re, num_im) = evaluate(feedforward);
+            let (den_re, den_im) = evaluate(feedback);
+            let den_norm = den_re * den_re + den_im * den_im;
+            let re = (num_re * den_re + num_im * den_im) / den_norm;
+            let im = (num_im * den_re - num_re * den_im) / den_norm;
+            ((re * re + im * im).sqrt() as f32, im.atan2(re) as f32)
+        })
+        .unzip()
+}
+
+impl AudioNodeEngine for IIRFilterNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::IIRFilterNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, _: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+        self.state
+            .resize(inputs.blocks[0].chan_count() as usize, Default::default());
+
+        // This node has tail time, so even if the block is silence
+        // we must still compute things on it.
+        let repeat_or_silence = inputs.blocks[0].is_silence() || inputs.blocks[0].is_repeat();
+
+        if repeat_or_silence && !self.state.iter().all(|s| *s == self.state[0]) {
+            // In case our input is repeat/silence but our states are not identical, we must
+            // explicitly duplicate, since mutate_with will otherwise only operate
+            // on the first channel, ignoring the states of the later ones
+            inputs.blocks[0].explicit_repeat();
+        } else {
+            // In case the states are identical, just make any silence explicit,
+            // since mutate_with can't handle silent blocks
+            inputs.blocks[0].explicit_silence();
+        }
+
+        {
+            let feedforward = &self.feedforward;
+            let feedback = &self.feedback;
+            let state = &mut self.state;
+            let mut iter = inputs.blocks[0].iter();
+            while let Some(mut frame) = iter.next() {
+                frame.mutate_with(|sample, chan| {
+                    let state = &mut state[chan as usize];
+                    let x0 = *sample as f64;
+                    let mut y0 = feedforward[0] * x0;
+                    for (b, x) in feedforward[1..].iter().zip(state.x.iter()) {
+                        y0 += b * x;
+                    }
+                    for (a, y) in feedback[1..].iter().zip(state.y.iter()) {
+                        y0 -= a * y;
+                    }
+                    *sample = y0 as f32;
+                    state.update(x0, y0);
+                });
+            }
+        }
+
+        if inputs.blocks[0].is_repeat() {
+            let state = self.state[0].clone();
+            self.state.iter_mut().for_each(|s| *s = state.clone());
+        }
+
+        inputs
+    }
+
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        !self.state.iter().all(IIRFilterState::is_quiescent)
+    }
+
+    make_message_handler!(IIRFilterNode: handle_message);
+}
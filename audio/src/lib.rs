@@ -11,21 +11,32 @@ extern crate byte_slice_cast;
 extern crate euclid;
 extern crate num_traits;
 extern crate petgraph;
+extern crate servo_media_player;
 extern crate smallvec;
 #[macro_use]
 pub mod macros;
 
 pub mod analyser_node;
+pub mod audio_worklet_node;
 pub mod biquad_filter_node;
 pub mod block;
 pub mod buffer_source_node;
 pub mod channel_node;
+pub mod constant_source_node;
 pub mod context;
+pub mod convolver_node;
 pub mod decoder;
+pub mod delay_node;
 pub mod destination_node;
+pub mod dynamics_compressor_node;
+mod fft;
 pub mod gain_node;
 pub mod graph;
+mod hrtf;
+pub mod iir_filter_node;
 pub mod listener;
+pub mod media_element_source_node;
+pub mod media_stream_destination_node;
 pub mod node;
 pub mod offline_sink;
 pub mod oscillator_node;
@@ -33,6 +44,8 @@ pub mod panner_node;
 pub mod param;
 pub mod render_thread;
 pub mod sink;
+pub mod stereo_panner_node;
+pub mod wave_shaper_node;
 
 pub trait AudioBackend {
     type Decoder: decoder::AudioDecoder;
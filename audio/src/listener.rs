@@ -1,7 +1,7 @@
 use block::{Block, Chunk};
 use node::{AudioNodeEngine, BlockInfo};
 use node::{AudioNodeType, ChannelInfo};
-use param::{Param, ParamDir, ParamType};
+use param::{Param, ParamDescriptor, ParamDir, ParamType};
 
 /// AudioListeners are fake nodes; from the user's point of view they're
 /// a non-node entity with zero inputs and outputs, but with AudioParams
@@ -29,17 +29,18 @@ pub(crate) struct AudioListenerNode {
 
 impl AudioListenerNode {
     pub fn new() -> Self {
+        let param = |val| Param::new(val, ParamDescriptor::unbounded(val));
         Self {
             channel_info: Default::default(),
-            position_x: Param::new(0.),
-            position_y: Param::new(0.),
-            position_z: Param::new(0.),
-            forward_x: Param::new(0.),
-            forward_y: Param::new(0.),
-            forward_z: Param::new(-1.),
-            up_x: Param::new(0.),
-            up_y: Param::new(1.),
-            up_z: Param::new(0.),
+            position_x: param(0.),
+            position_y: param(0.),
+            position_z: param(0.),
+            forward_x: param(0.),
+            forward_y: param(0.),
+            forward_z: param(-1.),
+            up_x: param(0.),
+            up_y: param(1.),
+            up_z: param(0.),
         }
     }
 }
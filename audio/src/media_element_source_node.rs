@@ -0,0 +1,234 @@
+use block::{Block, Chunk};
+use buffer_source_node::catmull_rom;
+use node::{AudioNodeEngine, AudioNodeType, BlockInfo, ChannelInfo};
+use servo_media_player::audio::AudioRenderer;
+use std::collections::VecDeque;
+use std::f64;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+
+/// The amount of audio, in seconds, we try to keep queued to absorb the
+/// jitter in the delivery of the player's audio
+const MIN_LATENCY: f64 = 0.01;
+/// If we never have less than this amount of audio queued, in seconds,
+/// the excess is dropped to get back in sync with the player, e.g. after
+/// the rendering thread stalled
+const MAX_LATENCY: f64 = 0.1;
+/// How often, in seconds, the playback speed is adjusted to compensate
+/// for the drift between the player's clock and ours
+const ADJUST_PERIOD: f64 = 0.5;
+/// The maximum adjustment of the playback speed, small enough to not be
+/// heard as a change in pitch
+const MAX_DRIFT: f64 = 0.005;
+
+/// A chunk of audio decoded by a player
+struct MediaChunk {
+    channels: Vec<Vec<f32>>,
+    sample_rate: u32,
+}
+
+/// Feeds the audio of a player to a MediaElementSourceNode. It is to be
+/// registered with `Player::register_audio_renderer`.
+pub struct MediaElementAudioRenderer {
+    sender: Sender<MediaChunk>,
+}
+
+impl AudioRenderer for MediaElementAudioRenderer {
+    fn render(&mut self, channels: Vec<Vec<f32>>, sample_rate: u32) {
+        // The node may be gone already
+        let _ = self.sender.send(MediaChunk {
+            channels,
+            sample_rate,
+        });
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#mediaelementaudiosourceoptions
+pub struct MediaElementSourceNodeOptions {
+    receiver: Receiver<MediaChunk>,
+}
+
+impl MediaElementSourceNodeOptions {
+    /// Options for a node playing the audio sent to the returned renderer
+    pub fn new() -> (Self, Arc<Mutex<MediaElementAudioRenderer>>) {
+        let (sender, receiver) = mpsc::channel();
+        let renderer = MediaElementAudioRenderer { sender };
+        (
+            MediaElementSourceNodeOptions { receiver },
+            Arc::new(Mutex::new(renderer)),
+        )
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#MediaElementAudioSourceNode
+#[derive(AudioNodeCommon)]
+pub(crate) struct MediaElementSourceNode {
+    channel_info: ChannelInfo,
+    receiver: Receiver<MediaChunk>,
+    /// The audio received from the player and not played yet, one queue
+    /// per channel. The frame before the playhead is kept for interpolation.
+    queue: Vec<VecDeque<f32>>,
+    /// The sample rate of the queued audio
+    input_rate: u32,
+    /// The index in the queue of the next frame to play, which is
+    /// fractional since we resample to the context's rate
+    playhead: f64,
+    /// The factor applied to the playback speed to compensate for drift
+    speed: f64,
+    /// The least amount of queued audio, in seconds, seen since the
+    /// beginning of the current adjustment period
+    low_water: f64,
+    /// Whether we received anything during the current adjustment period
+    received: bool,
+    /// The time at which the current adjustment period began
+    period_start: f64,
+    /// Whether the player is gone, and won't send anything anymore
+    disconnected: bool,
+}
+
+impl MediaElementSourceNode {
+    pub fn new(options: MediaElementSourceNodeOptions, channel_info: ChannelInfo) -> Self {
+        Self {
+            channel_info,
+            receiver: options.receiver,
+            queue: Vec::new(),
+            input_rate: 0,
+            playhead: 0.,
+            speed: 1.,
+            low_water: f64::INFINITY,
+            received: false,
+            period_start: 0.,
+            disconnected: false,
+        }
+    }
+
+    /// The amount of audio, in seconds, left to play
+    fn queued(&self) -> f64 {
+        match self.queue.first() {
+            Some(queue) if self.input_rate > 0 => {
+                (queue.len() as f64 - 1. - self.playhead).max(0.) / self.input_rate as f64
+            }
+            _ => 0.,
+        }
+    }
+
+    fn receive(&mut self) {
+        loop {
+            let chunk = match self.receiver.try_recv() {
+                Ok(chunk) => chunk,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    break;
+                }
+            };
+            if chunk.channels.is_empty() || chunk.sample_rate == 0 {
+                continue;
+            }
+            if chunk.channels.len() != self.queue.len() || chunk.sample_rate != self.input_rate {
+                // The format of the media changed, start over
+                self.queue = vec![VecDeque::new(); chunk.channels.len()];
+                self.input_rate = chunk.sample_rate;
+                self.playhead = 0.;
+            }
+            let frames = chunk.channels.iter().map(|c| c.len()).min().unwrap_or(0);
+            for (queue, data) in self.queue.iter_mut().zip(&chunk.channels) {
+                queue.extend(&data[..frames]);
+            }
+            self.received = true;
+        }
+    }
+
+    /// Speed playback up or down to keep the queue short, without ever
+    /// running out of audio
+    fn adjust_speed(&mut self, info: &BlockInfo) {
+        self.low_water = self.low_water.min(self.queued());
+        if info.time - self.period_start < ADJUST_PERIOD {
+            return;
+        }
+        self.speed = if self.low_water > MAX_LATENCY {
+            // Way behind, skip ahead instead of slowly catching up
+            let skip = ((self.low_water - MIN_LATENCY) * self.input_rate as f64) as usize;
+            for queue in &mut self.queue {
+                queue.drain(..skip);
+            }
+            1.
+        } else if self.received {
+            let error = (self.low_water - MIN_LATENCY) / ADJUST_PERIOD;
+            1. + error.max(-MAX_DRIFT).min(MAX_DRIFT)
+        } else {
+            // The player is paused or done, there is no drift to measure
+            1.
+        };
+        self.low_water = f64::INFINITY;
+        self.received = false;
+        self.period_start = info.time;
+    }
+}
+
+impl AudioNodeEngine for MediaElementSourceNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::MediaElementSourceNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 0);
+
+        self.receive();
+
+        if self.queue.is_empty() {
+            inputs.blocks.push(Default::default());
+            self.adjust_speed(info);
+            return inputs;
+        }
+
+        let mut block = Block::for_channels_explicit(self.queue.len() as u8);
+        let step = self.input_rate as f64 / info.sample_rate as f64 * self.speed;
+        let len = self.queue[0].len();
+        {
+            let mut iter = block.iter();
+            while let Some(mut frame) = iter.next() {
+                let index = self.playhead.floor() as usize;
+                if index + 1 >= len {
+                    // Ran out of audio, the rest of the block is silent
+                    break;
+                }
+                let fraction = (self.playhead - index as f64) as f32;
+                let prev = index.max(1) - 1;
+                let next = (index + 2).min(len - 1);
+                let queue = &self.queue;
+                frame.mutate_with(|sample, chan| {
+                    let queue = &queue[chan as usize];
+                    *sample = catmull_rom(
+                        queue[prev],
+                        queue[index],
+                        queue[index + 1],
+                        queue[next],
+                        fraction,
+                    );
+                });
+                self.playhead += step;
+            }
+        }
+
+        // Drop what was played, except for the frame before the playhead
+        let played = (self.playhead.floor() as usize).min(len).max(1) - 1;
+        for queue in &mut self.queue {
+            queue.drain(..played);
+        }
+        self.playhead -= played as f64;
+
+        self.adjust_speed(info);
+
+        inputs.blocks.push(block);
+        inputs
+    }
+
+    fn input_count(&self) -> u32 {
+        0
+    }
+
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        !self.disconnected || self.queued() > 0.
+    }
+}
@@ -0,0 +1,123 @@
+use block::{Block, Chunk};
+use node::{AudioNodeEngine, BlockInfo};
+use node::{AudioNodeType, ChannelCountMode, ChannelInfo};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+/// Receives the audio rendered by a MediaStreamDestinationNode, on the
+/// rendering thread
+pub trait MediaStreamConsumer: Send {
+    /// Consume a block of audio. This is called for every rendered block,
+    /// even silent ones.
+    fn push(&mut self, block: Block, info: &BlockInfo);
+}
+
+/// How the channels of a MediaStreamBuffer are laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleLayout {
+    /// The samples of every channel for the first frame, then for the
+    /// second one, and so on
+    Interleaved,
+    /// All the samples of the first channel, then of the second one, and
+    /// so on
+    Planar,
+}
+
+/// A block of audio sent through the channel of a MediaStreamDestinationNode
+pub struct MediaStreamBuffer {
+    pub data: Vec<f32>,
+    pub channels: u8,
+    pub layout: SampleLayout,
+    pub sample_rate: f32,
+    /// The context time of the first frame
+    pub time: f64,
+}
+
+struct ChannelConsumer {
+    sender: SyncSender<MediaStreamBuffer>,
+    layout: SampleLayout,
+}
+
+impl MediaStreamConsumer for ChannelConsumer {
+    fn push(&mut self, mut block: Block, info: &BlockInfo) {
+        let data = match self.layout {
+            SampleLayout::Interleaved => block.interleave(),
+            SampleLayout::Planar => {
+                block.explicit_repeat();
+                block.data_mut().to_vec()
+            }
+        };
+        // The receiving end may be gone already, or not keeping up, in
+        // which case the block is dropped rather than queued up forever
+        let _ = self.sender.try_send(MediaStreamBuffer {
+            data,
+            channels: block.chan_count(),
+            layout: self.layout,
+            sample_rate: info.sample_rate,
+            time: info.time,
+        });
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#MediaStreamAudioDestinationNode
+pub struct MediaStreamDestinationNodeOptions {
+    consumer: Box<MediaStreamConsumer>,
+}
+
+impl MediaStreamDestinationNodeOptions {
+    pub fn new(consumer: Box<MediaStreamConsumer>) -> Self {
+        MediaStreamDestinationNodeOptions { consumer }
+    }
+
+    /// Options for a node sending its audio through the returned channel
+    ///
+    /// At most `capacity` blocks are queued in the channel, blocks rendered
+    /// while it is full are dropped.
+    pub fn channel(layout: SampleLayout, capacity: usize) -> (Self, Receiver<MediaStreamBuffer>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let consumer = ChannelConsumer { sender, layout };
+        (Self::new(Box::new(consumer)), receiver)
+    }
+}
+
+#[derive(AudioNodeCommon)]
+pub(crate) struct MediaStreamDestinationNode {
+    channel_info: ChannelInfo,
+    consumer: Box<MediaStreamConsumer>,
+}
+
+impl MediaStreamDestinationNode {
+    pub fn new(options: MediaStreamDestinationNodeOptions, channel_info: ChannelInfo) -> Self {
+        Self {
+            // Like the context's destination, the stream has a fixed layout
+            // https://webaudio.github.io/web-audio-api/#MediaStreamAudioDestinationNode
+            channel_info: ChannelInfo {
+                mode: ChannelCountMode::Explicit,
+                ..channel_info
+            },
+            consumer: options.consumer,
+        }
+    }
+}
+
+impl AudioNodeEngine for MediaStreamDestinationNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::MediaStreamDestinationNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+
+        let mut block = inputs.blocks[0].take();
+        block.explicit_silence();
+        self.consumer.push(block, info);
+        Chunk::default()
+    }
+
+    fn output_count(&self) -> u32 {
+        0
+    }
+
+    fn is_sink(&self) -> bool {
+        true
+    }
+}
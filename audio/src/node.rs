@@ -1,34 +1,79 @@
+use analyser_node::{AnalyserNodeMessage, AnalyserNodeOptions};
+use audio_worklet_node::AudioWorkletNodeOptions;
 use biquad_filter_node::{BiquadFilterNodeMessage, BiquadFilterNodeOptions};
 use block::{Block, Chunk, Tick};
 use boxfnonce::SendBoxFnOnce;
 use buffer_source_node::{AudioBufferSourceNodeMessage, AudioBufferSourceNodeOptions};
 use channel_node::ChannelNodeOptions;
+use constant_source_node::ConstantSourceNodeOptions;
+use convolver_node::{ConvolverNodeMessage, ConvolverNodeOptions};
+use delay_node::DelayNodeOptions;
+use dynamics_compressor_node::{DynamicsCompressorNodeMessage, DynamicsCompressorNodeOptions};
 use gain_node::GainNodeOptions;
-use oscillator_node::OscillatorNodeOptions;
+use iir_filter_node::{IIRFilterNodeMessage, IIRFilterNodeOptions};
+use media_element_source_node::MediaElementSourceNodeOptions;
+use media_stream_destination_node::MediaStreamDestinationNodeOptions;
+use oscillator_node::{OscillatorNodeMessage, OscillatorNodeOptions};
 use panner_node::{PannerNodeMessage, PannerNodeOptions};
-use param::{Param, ParamRate, ParamType, UserAutomationEvent};
+use param::{Param, ParamDescriptor, ParamRate, ParamType, UserAutomationEvent};
 use std::sync::mpsc::Sender;
+use stereo_panner_node::StereoPannerNodeOptions;
+use wave_shaper_node::{WaveShaperNodeMessage, WaveShaperNodeOptions};
 
 /// Information required to construct an audio node
 pub enum AudioNodeInit {
     AnalyserNode(Box<FnMut(Block) + Send>),
+    /// An AnalyserNode running its AnalysisEngine on the rendering
+    /// thread, to be queried with AnalyserNodeMessages
+    AnalyserEngineNode(AnalyserNodeOptions),
     BiquadFilterNode(BiquadFilterNodeOptions),
     AudioBuffer,
     AudioBufferSourceNode(AudioBufferSourceNodeOptions),
+    AudioWorkletNode(AudioWorkletNodeOptions),
     ChannelMergerNode(ChannelNodeOptions),
     ChannelSplitterNode,
-    ConstantSourceNode,
-    ConvolverNode,
-    DelayNode,
-    DynamicsCompressionNode,
+    ConstantSourceNode(ConstantSourceNodeOptions),
+    ConvolverNode(ConvolverNodeOptions),
+    DelayNode(DelayNodeOptions),
+    DynamicsCompressionNode(DynamicsCompressorNodeOptions),
     GainNode(GainNodeOptions),
-    IIRFilterNode,
+    IIRFilterNode(IIRFilterNodeOptions),
+    MediaElementSourceNode(MediaElementSourceNodeOptions),
+    MediaStreamDestinationNode(MediaStreamDestinationNodeOptions),
     OscillatorNode(OscillatorNodeOptions),
     PannerNode(PannerNodeOptions),
     PeriodicWave,
     ScriptProcessorNode,
-    StereoPannerNode,
-    WaveShaperNode,
+    StereoPannerNode(StereoPannerNodeOptions),
+    WaveShaperNode(WaveShaperNodeOptions),
+}
+
+impl AudioNodeInit {
+    /// Check the options the spec makes node constructors throw on, which
+    /// the rendering thread can't deal with
+    pub fn validate(&self) -> Result<(), AudioNodeError> {
+        match *self {
+            AudioNodeInit::AnalyserEngineNode(ref options) => options.validate(),
+            AudioNodeInit::AudioWorkletNode(ref options) => options.validate(),
+            AudioNodeInit::ConvolverNode(ref options) => options.validate(),
+            AudioNodeInit::IIRFilterNode(ref options) => options.validate(),
+            AudioNodeInit::OscillatorNode(ref options) => options.validate(),
+            AudioNodeInit::WaveShaperNode(ref options) => options.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Why a node can't be created with the given options, or can't be sent
+/// a message, named after the exception the spec throws in that case.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AudioNodeError {
+    /// https://heycam.github.io/webidl/#notsupportederror
+    NotSupported,
+    /// https://heycam.github.io/webidl/#invalidstateerror
+    InvalidState,
+    /// https://heycam.github.io/webidl/#indexsizeerror
+    IndexSize,
 }
 
 /// Type of AudioNodeEngine.
@@ -40,6 +85,7 @@ pub enum AudioNodeType {
     BiquadFilterNode,
     AudioBuffer,
     AudioBufferSourceNode,
+    AudioWorkletNode,
     ChannelMergerNode,
     ChannelSplitterNode,
     ConstantSourceNode,
@@ -49,6 +95,8 @@ pub enum AudioNodeType {
     DynamicsCompressionNode,
     GainNode,
     IIRFilterNode,
+    MediaElementSourceNode,
+    MediaStreamDestinationNode,
     OscillatorNode,
     PannerNode,
     PeriodicWave,
@@ -122,9 +170,18 @@ pub(crate) trait AudioNodeEngine: Send + AudioNodeCommon {
             AudioNodeMessage::SetChannelMode(c) => self.set_channel_count_mode(c),
             AudioNodeMessage::SetChannelInterpretation(c) => self.set_channel_interpretation(c),
             AudioNodeMessage::SetParam(id, event) => {
-                self.get_param(id).insert_event(event.to_event(sample_rate))
+                if let Err(e) = self.get_param(id).insert_event(event.to_event(sample_rate)) {
+                    warn!("Ignoring automation event for {:?}: {:?}", id, e);
+                }
+            }
+            AudioNodeMessage::GetParamDescriptor(id, tx) => {
+                let _ = tx.send(self.get_param(id).descriptor());
+            }
+            AudioNodeMessage::SetParamRate(id, rate) => {
+                if let Err(e) = self.get_param(id).set_rate(rate) {
+                    warn!("Ignoring automation rate for {:?}: {:?}", id, e);
+                }
             }
-            AudioNodeMessage::SetParamRate(id, rate) => self.get_param(id).set_rate(rate),
             _ => self.message_specific(msg, sample_rate),
         }
     }
@@ -174,14 +231,63 @@ pub(crate) trait AudioNodeEngine: Send + AudioNodeCommon {
     fn set_listenerdata(&mut self, _: Block) {
         panic!("{:?} can't accept listener connections")
     }
+
+    /// Whether this node is allowed to be part of a cycle in the graph.
+    ///
+    /// Only DelayNodes can do this
+    /// https://webaudio.github.io/web-audio-api/#DelayNode
+    fn breaks_cycles(&self) -> bool {
+        false
+    }
+
+    /// Whether this node consumes its inputs like the destination does,
+    /// and thus has to be processed without being connected to it
+    ///
+    /// https://webaudio.github.io/web-audio-api/#MediaStreamAudioDestinationNode
+    fn is_sink(&self) -> bool {
+        false
+    }
+
+    /// Whether this node may still output sound without any input, after
+    /// the block described by `info`: a source node which hasn't finished
+    /// playing yet, or a node whose tail time hasn't elapsed
+    ///
+    /// Nodes released by the control thread are removed from the graph
+    /// once this is false and they have no inputs left.
+    ///
+    /// https://webaudio.github.io/web-audio-api/#actively-processing
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        false
+    }
+
+    /// For nodes that break cycles, produce the output for this block
+    /// before the input for this block is known
+    ///
+    /// The input will be provided afterwards through `process_cycle_input()`
+    fn process_cycle_output(&mut self, _: &BlockInfo) -> Chunk {
+        panic!("{:?} can't be part of a cycle", self.node_type())
+    }
+
+    /// For nodes that break cycles, consume the input for this block
+    /// after its output has been obtained through `process_cycle_output()`
+    fn process_cycle_input(&mut self, _: Chunk, _: &BlockInfo) {
+        panic!("{:?} can't be part of a cycle", self.node_type())
+    }
 }
 
 pub enum AudioNodeMessage {
+    AnalyserNode(AnalyserNodeMessage),
     AudioBufferSourceNode(AudioBufferSourceNodeMessage),
     AudioScheduledSourceNode(AudioScheduledSourceNodeMessage),
     BiquadFilterNode(BiquadFilterNodeMessage),
+    ConvolverNode(ConvolverNodeMessage),
+    DynamicsCompressionNode(DynamicsCompressorNodeMessage),
+    IIRFilterNode(IIRFilterNodeMessage),
+    OscillatorNode(OscillatorNodeMessage),
     PannerNode(PannerNodeMessage),
+    WaveShaperNode(WaveShaperNodeMessage),
     GetParamValue(ParamType, Sender<f32>),
+    GetParamDescriptor(ParamType, Sender<ParamDescriptor>),
     SetChannelCount(u8),
     SetChannelMode(ChannelCountMode),
     SetChannelInterpretation(ChannelInterpretation),
@@ -189,6 +295,20 @@ pub enum AudioNodeMessage {
     SetParamRate(ParamType, ParamRate),
 }
 
+impl AudioNodeMessage {
+    /// Check the values the spec makes setters throw on, which the
+    /// rendering thread can't deal with
+    pub fn validate(&self) -> Result<(), AudioNodeError> {
+        match *self {
+            AudioNodeMessage::AnalyserNode(ref message) => message.validate(),
+            AudioNodeMessage::ConvolverNode(ref message) => message.validate(),
+            AudioNodeMessage::OscillatorNode(ref message) => message.validate(),
+            AudioNodeMessage::WaveShaperNode(ref message) => message.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
 pub struct OnEndedCallback(pub SendBoxFnOnce<'static, ()>);
 
 impl OnEndedCallback {
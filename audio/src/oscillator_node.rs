@@ -1,15 +1,136 @@
 use block::{Chunk, Tick};
-use node::{AudioNodeEngine, AudioScheduledSourceNodeMessage, BlockInfo, OnEndedCallback};
+use fft::{Complex, Fft};
+use node::{AudioNodeEngine, AudioNodeError, BlockInfo};
 use node::{AudioNodeType, ChannelInfo, ShouldPlay};
+use node::{AudioScheduledSourceNodeMessage, OnEndedCallback};
 use num_traits::cast::NumCast;
-use param::{Param, ParamType};
+use param::{Param, ParamDescriptor, ParamType};
+use std::sync::Arc;
 
-#[derive(Copy, Clone, Debug)]
+/// https://webaudio.github.io/web-audio-api/#dictdef-periodicwaveoptions
+#[derive(Clone, Debug)]
 pub struct PeriodicWaveOptions {
-    // XXX https://webaudio.github.io/web-audio-api/#dictdef-periodicwaveoptions
+    /// The cosine terms of the Fourier series, the first (DC) term is ignored
+    pub real: Vec<f32>,
+    /// The sine terms of the Fourier series, the first term is ignored
+    pub imag: Vec<f32>,
+    /// If false, the waveform is scaled to have a peak of 1
+    pub disable_normalization: bool,
 }
 
-#[derive(Copy, Clone, Debug)]
+impl PeriodicWaveOptions {
+    /// https://webaudio.github.io/web-audio-api/#dom-periodicwave-periodicwave
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        if self.real.len() != self.imag.len() || self.real.len() < 2 {
+            return Err(AudioNodeError::IndexSize);
+        }
+        Ok(())
+    }
+}
+
+impl Default for PeriodicWaveOptions {
+    fn default() -> Self {
+        // A sine wave
+        PeriodicWaveOptions {
+            real: vec![0., 0.],
+            imag: vec![0., 1.],
+            disable_normalization: false,
+        }
+    }
+}
+
+/// The number of samples in one period of a wavetable
+const TABLE_SIZE: usize = 2048;
+
+/// A periodic waveform, as a set of band-limited wavetables
+///
+/// https://webaudio.github.io/web-audio-api/#PeriodicWave
+pub(crate) struct PeriodicWave {
+    /// `tables[k]` only contains the first 2^k partials, so that it
+    /// can be played without aliasing as long as 2^k times its
+    /// frequency stays below the nyquist frequency
+    tables: Vec<Vec<f32>>,
+}
+
+impl PeriodicWave {
+    pub fn new(options: &PeriodicWaveOptions) -> Self {
+        let len = options.real.len().max(options.imag.len());
+        let coefficient =
+            |coefficients: &[f32], n: usize| coefficients.get(n).cloned().unwrap_or(0.);
+        // The nyquist bin is left out, it can only hold cosine terms
+        let partials = if len > 1 {
+            (len - 1).min(TABLE_SIZE / 2 - 1)
+        } else {
+            1
+        };
+        let table_count = (partials as f32).log2().ceil() as usize + 1;
+
+        let fft = Fft::new(TABLE_SIZE);
+        let mut data = vec![Complex::default(); TABLE_SIZE];
+        let mut tables: Vec<Vec<f32>> = (0..table_count)
+            .map(|k| {
+                let limit = (1 << k).min(partials);
+                for x in data.iter_mut() {
+                    *x = Complex::default();
+                }
+                // With X[n] = N/2 * (a_n - i b_n), the inverse transform is
+                // the sum of a_n cos(2πnt) + b_n sin(2πnt)
+                let half = TABLE_SIZE as f32 / 2.;
+                for n in 1..limit + 1 {
+                    let x = Complex::new(
+                        half * coefficient(&options.real, n),
+                        -half * coefficient(&options.imag, n),
+                    );
+                    data[n] = x;
+                    data[TABLE_SIZE - n] = x.conj();
+                }
+                fft.inverse(&mut data);
+                data.iter().map(|x| x.re).collect()
+            })
+            .collect();
+
+        // https://webaudio.github.io/web-audio-api/#waveform-normalization
+        // All the tables are scaled the same way, so that the
+        // loudness doesn't change with the frequency
+        if !options.disable_normalization {
+            let max = tables[table_count - 1]
+                .iter()
+                .fold(0., |max: f32, x| max.max(x.abs()));
+            if max > 0. {
+                for table in tables.iter_mut() {
+                    for x in table.iter_mut() {
+                        *x /= max;
+                    }
+                }
+            }
+        }
+
+        PeriodicWave { tables }
+    }
+
+    /// The index of the table with the most partials that can be
+    /// played at this frequency without aliasing
+    fn table_index(&self, frequency: f64, sample_rate: f64) -> usize {
+        let partials = sample_rate / 2. / frequency.abs();
+        if !(partials >= 2.) {
+            return 0;
+        }
+        (partials.log2().floor() as usize).min(self.tables.len() - 1)
+    }
+
+    /// The value of the waveform in the given table, for a phase in [0, 1)
+    fn value(&self, table: usize, phase: f64) -> f32 {
+        let table = &self.tables[table];
+        let position = phase * TABLE_SIZE as f64;
+        let index = position.floor();
+        let fraction = (position - index) as f32;
+        let index = index as usize % TABLE_SIZE;
+        let next = (index + 1) % TABLE_SIZE;
+        table[index] + (table[next] - table[index]) * fraction
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum OscillatorType {
     Sine,
     Square,
@@ -18,7 +139,59 @@ pub enum OscillatorType {
     Custom,
 }
 
-#[derive(Copy, Clone, Debug)]
+/// The waveforms of the built-in oscillator types
+///
+/// They don't depend on the sample rate, so they are computed once by
+/// the rendering thread and shared by all of its oscillators.
+pub(crate) struct BuiltinPeriodicWaves {
+    square: Arc<PeriodicWave>,
+    sawtooth: Arc<PeriodicWave>,
+    triangle: Arc<PeriodicWave>,
+}
+
+impl BuiltinPeriodicWaves {
+    pub fn new() -> Self {
+        BuiltinPeriodicWaves {
+            square: Arc::new(builtin_periodic_wave(OscillatorType::Square)),
+            sawtooth: Arc::new(builtin_periodic_wave(OscillatorType::Sawtooth)),
+            triangle: Arc::new(builtin_periodic_wave(OscillatorType::Triangle)),
+        }
+    }
+
+    /// The waveform of a built-in oscillator type, sines are computed
+    /// directly so they don't need one
+    fn get(&self, oscillator_type: OscillatorType) -> Option<Arc<PeriodicWave>> {
+        match oscillator_type {
+            OscillatorType::Sine | OscillatorType::Custom => None,
+            OscillatorType::Square => Some(self.square.clone()),
+            OscillatorType::Sawtooth => Some(self.sawtooth.clone()),
+            OscillatorType::Triangle => Some(self.triangle.clone()),
+        }
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#oscillator-coefficients
+fn builtin_periodic_wave(oscillator_type: OscillatorType) -> PeriodicWave {
+    use std::f32::consts::PI;
+
+    let coefficient: fn(f32) -> f32 = match oscillator_type {
+        OscillatorType::Sine | OscillatorType::Custom => unreachable!(),
+        OscillatorType::Square => |n| 2. / (n * PI) * (1. - (-1f32).powf(n)),
+        OscillatorType::Sawtooth => |n| -(-1f32).powf(n) * 2. / (n * PI),
+        OscillatorType::Triangle => |n| 8. * (n * PI / 2.).sin() / (PI * n).powi(2),
+    };
+    let len = TABLE_SIZE / 2;
+    let imag = (0..len)
+        .map(|n| if n == 0 { 0. } else { coefficient(n as f32) })
+        .collect();
+    PeriodicWave::new(&PeriodicWaveOptions {
+        real: vec![0.; len],
+        imag,
+        disable_normalization: false,
+    })
+}
+
+#[derive(Clone, Debug)]
 pub struct OscillatorNodeOptions {
     pub oscillator_type: OscillatorType,
     pub freq: f32,
@@ -26,6 +199,19 @@ pub struct OscillatorNodeOptions {
     pub periodic_wave_options: Option<PeriodicWaveOptions>,
 }
 
+impl OscillatorNodeOptions {
+    /// https://webaudio.github.io/web-audio-api/#dom-oscillatornode-oscillatornode
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        match self.periodic_wave_options {
+            Some(ref options) => options.validate(),
+            None if self.oscillator_type == OscillatorType::Custom => {
+                Err(AudioNodeError::InvalidState)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
 impl Default for OscillatorNodeOptions {
     fn default() -> Self {
         OscillatorNodeOptions {
@@ -37,12 +223,38 @@ impl Default for OscillatorNodeOptions {
     }
 }
 
+/// Type of message directed to OscillatorNodes.
+#[derive(Clone, Debug)]
+pub enum OscillatorNodeMessage {
+    /// Change the type of waveform, to one of the built-in ones
+    SetOscillatorType(OscillatorType),
+    /// Play the given waveform, switching the type to Custom
+    SetPeriodicWave(PeriodicWaveOptions),
+}
+
+impl OscillatorNodeMessage {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        match *self {
+            // https://webaudio.github.io/web-audio-api/#dom-oscillatornode-type
+            OscillatorNodeMessage::SetOscillatorType(OscillatorType::Custom) => {
+                Err(AudioNodeError::InvalidState)
+            }
+            OscillatorNodeMessage::SetOscillatorType(_) => Ok(()),
+            OscillatorNodeMessage::SetPeriodicWave(ref options) => options.validate(),
+        }
+    }
+}
+
 #[derive(AudioScheduledSourceNode, AudioNodeCommon)]
 pub(crate) struct OscillatorNode {
     channel_info: ChannelInfo,
+    oscillator_type: OscillatorType,
     frequency: Param,
     detune: Param,
     phase: f64,
+    /// The waveform to play, a sine if None
+    periodic_wave: Option<Arc<PeriodicWave>>,
+    builtin_periodic_waves: Arc<BuiltinPeriodicWaves>,
     /// Time at which the source should start playing.
     start_at: Option<Tick>,
     /// Time at which the source should stop playing.
@@ -52,20 +264,77 @@ pub(crate) struct OscillatorNode {
 }
 
 impl OscillatorNode {
-    pub fn new(options: OscillatorNodeOptions, channel_info: ChannelInfo) -> Self {
+    pub fn new(
+        options: OscillatorNodeOptions,
+        channel_info: ChannelInfo,
+        sample_rate: f32,
+        builtin_periodic_waves: Arc<BuiltinPeriodicWaves>,
+    ) -> Self {
+        debug_assert!(options.validate().is_ok());
+        let nyquist = sample_rate / 2.;
+        let periodic_wave = match options.oscillator_type {
+            OscillatorType::Custom => Some(Arc::new(PeriodicWave::new(
+                &options.periodic_wave_options.unwrap_or_default(),
+            ))),
+            oscillator_type => builtin_periodic_waves.get(oscillator_type),
+        };
         Self {
             channel_info,
-            frequency: Param::new(options.freq.into()),
-            detune: Param::new(options.detune.into()),
+            oscillator_type: options.oscillator_type,
+            frequency: Param::new(
+                options.freq.into(),
+                ParamDescriptor::new(440., -nyquist, nyquist),
+            ),
+            detune: Param::new(options.detune.into(), ParamDescriptor::detune()),
             phase: 0.,
+            periodic_wave,
+            builtin_periodic_waves,
             start_at: None,
             stop_at: None,
             onended_callback: None,
         }
     }
 
-    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
-        self.frequency.update(info, tick)
+    pub fn update_parameters(&mut self, info: &BlockInfo) -> bool {
+        let mut changed = self.frequency.update_block(info);
+        changed |= self.detune.update_block(info);
+        changed
+    }
+
+    /// The detuned frequency at a tick of the block, clamped to the
+    /// nominal range
+    ///
+    /// https://webaudio.github.io/web-audio-api/#dom-oscillatornode-frequency
+    fn computed_frequency(&self, tick: Tick, sample_rate: f32) -> f64 {
+        let frequency = self.frequency.block_value(tick) as f64
+            * 2f64.powf(self.detune.block_value(tick) as f64 / 1200.);
+        let nyquist = sample_rate as f64 / 2.;
+        if frequency > nyquist || !frequency.is_finite() {
+            nyquist
+        } else if frequency < -nyquist {
+            -nyquist
+        } else {
+            frequency
+        }
+    }
+
+    pub fn handle_message(&mut self, message: OscillatorNodeMessage, _: f32) {
+        match message {
+            OscillatorNodeMessage::SetOscillatorType(oscillator_type) => {
+                // Custom waveforms can only be set with SetPeriodicWave
+                if oscillator_type == self.oscillator_type
+                    || oscillator_type == OscillatorType::Custom
+                {
+                    return;
+                }
+                self.oscillator_type = oscillator_type;
+                self.periodic_wave = self.builtin_periodic_waves.get(oscillator_type);
+            }
+            OscillatorNodeMessage::SetPeriodicWave(options) => {
+                self.oscillator_type = OscillatorType::Custom;
+                self.periodic_wave = Some(Arc::new(PeriodicWave::new(&options)));
+            }
+        }
     }
 }
 
@@ -75,9 +344,6 @@ impl AudioNodeEngine for OscillatorNode {
     }
 
     fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
-        // XXX Implement this properly and according to self.options
-        // as defined in https://webaudio.github.io/web-audio-api/#oscillatornode
-
         use std::f64::consts::PI;
 
         debug_assert!(inputs.len() == 0);
@@ -91,6 +357,10 @@ impl AudioNodeEngine for OscillatorNode {
             ShouldPlay::Between(start, end) => (start, end)
         };
 
+        self.update_parameters(info);
+        // Constant params only need the frequency to be computed once
+        let constant = self.frequency.is_constant() && self.detune.is_constant();
+
         {
             inputs.blocks[0].explicit_silence();
             let mut iter = inputs.blocks[0].iter();
@@ -105,7 +375,13 @@ impl AudioNodeEngine for OscillatorNode {
             // converted to floating point numbers and then iterated over in 1-steps
             //
             // Also, if the frequency changes the phase should not
-            let mut step = two_pi * self.frequency.value() as f64 / sample_rate;
+            let table_index = |wave: &Option<Arc<PeriodicWave>>, frequency: f64| {
+                wave.as_ref()
+                    .map_or(0, |wave| wave.table_index(frequency, sample_rate))
+            };
+            let mut frequency = self.computed_frequency(Tick(0), info.sample_rate);
+            let mut step = two_pi * frequency / sample_rate;
+            let mut table = table_index(&self.periodic_wave, frequency);
             while let Some(mut frame) = iter.next() {
                 let tick = frame.tick();
                 if tick < start_at {
@@ -113,16 +389,27 @@ impl AudioNodeEngine for OscillatorNode {
                 } else if tick > stop_at {
                     break;
                 }
-
-                if self.update_parameters(info, tick) {
-                    step = two_pi * self.frequency.value() as f64 / sample_rate;
+                if !constant {
+                    let new_frequency = self.computed_frequency(tick, info.sample_rate);
+                    if new_frequency != frequency {
+                        frequency = new_frequency;
+                        step = two_pi * frequency / sample_rate;
+                        table = table_index(&self.periodic_wave, frequency);
+                    }
                 }
-                let value = vol * f32::sin(NumCast::from(self.phase).unwrap());
+
+                let value = match self.periodic_wave {
+                    Some(ref wave) => wave.value(table, self.phase / two_pi),
+                    None => f32::sin(NumCast::from(self.phase).unwrap()),
+                };
+                let value = vol * value;
                 frame.mutate_with(|sample, _| *sample = value);
 
                 self.phase += step;
                 if self.phase >= two_pi {
                     self.phase -= two_pi;
+                } else if self.phase < 0. {
+                    self.phase += two_pi;
                 }
             }
         }
@@ -133,6 +420,10 @@ impl AudioNodeEngine for OscillatorNode {
         0
     }
 
+    fn has_remaining_output(&self, info: &BlockInfo) -> bool {
+        self.plays_after(info.frame + Tick::FRAMES_PER_BLOCK)
+    }
+
     fn get_param(&mut self, id: ParamType) -> &mut Param {
         match id {
             ParamType::Frequency => &mut self.frequency,
@@ -141,5 +432,8 @@ impl AudioNodeEngine for OscillatorNode {
         }
     }
 
-    make_message_handler!(AudioScheduledSourceNode: handle_source_node_message);
+    make_message_handler!(
+        OscillatorNode: handle_message,
+        AudioScheduledSourceNode: handle_source_node_message
+    );
 }
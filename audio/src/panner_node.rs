@@ -1,9 +1,11 @@
-use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK};
+use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK, FRAMES_PER_BLOCK_USIZE};
 use euclid::Vector3D;
+use hrtf::{HrtfDatabase, HrtfPanner, TAIL_FRAMES};
 use node::{AudioNodeEngine, AudioNodeMessage, BlockInfo};
 use node::{AudioNodeType, ChannelInfo};
-use param::{Param, ParamDir, ParamType};
+use param::{Param, ParamDescriptor, ParamDir, ParamType};
 use std::f32::consts::PI;
+use std::sync::Arc;
 
 // .normalize(), but it takes into account zero vectors
 fn normalize_zero(v: Vector3D<f32>) -> Vector3D<f32> {
@@ -15,6 +17,49 @@ fn normalize_zero(v: Vector3D<f32>) -> Vector3D<f32> {
     }
 }
 
+/// Whether an AudioListener-produced block is the same for every frame
+fn is_constant(listener_data: &Block) -> bool {
+    listener_data.is_silence()
+        || (0..listener_data.chan_count()).all(|chan| {
+            let data = listener_data.data_chan(chan);
+            data.iter().all(|v| *v == data[0])
+        })
+}
+
+/// Pan a single frame with equal-power panning, for a pan position
+/// in [-1, 1]. For mono input, `l` is the input and `r` is ignored.
+///
+/// https://webaudio.github.io/web-audio-api/#Spatialization-equal-power-panning
+/// https://webaudio.github.io/web-audio-api/#stereopanner-algorithm
+pub(crate) fn equal_power_pan(pan: f32, mono: bool, l: f32, r: f32) -> (f32, f32) {
+    let x = if mono {
+        (pan + 1.) / 2.
+    } else if pan <= 0. {
+        pan + 1.
+    } else {
+        pan
+    };
+    let x = x * PI / 2.;
+
+    let mut gain_l = x.cos();
+    let mut gain_r = x.sin();
+    // 9. * PI / 2 is often slightly negative, clamp
+    if gain_l <= 0. {
+        gain_l = 0.
+    }
+    if gain_r <= 0. {
+        gain_r = 0.;
+    }
+
+    if mono {
+        (l * gain_l, l * gain_r)
+    } else if pan <= 0. {
+        (l + r * gain_l, r * gain_r)
+    } else {
+        (l * gain_l, r + l * gain_r)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PanningModel {
     EqualPower,
@@ -96,20 +141,31 @@ pub(crate) struct PannerNode {
     cone_outer_angle: f64,
     cone_outer_gain: f64,
     listener_data: Option<Block>,
+    hrtf_database: Arc<HrtfDatabase>,
+    /// Created the first time the HRTF panning model is used
+    hrtf: Option<Box<HrtfPanner>>,
+    /// The number of silent frames received since the last non-silent
+    /// input
+    silent_frames: usize,
 }
 
 impl PannerNode {
-    pub fn new(options: PannerNodeOptions, channel_info: ChannelInfo) -> Self {
+    pub fn new(
+        options: PannerNodeOptions,
+        channel_info: ChannelInfo,
+        hrtf_database: Arc<HrtfDatabase>,
+    ) -> Self {
+        let param = |val, default| Param::new(val, ParamDescriptor::unbounded(default));
         Self {
             channel_info,
             panning_model: options.panning_model,
             distance_model: options.distance_model,
-            position_x: Param::new(options.position_x),
-            position_y: Param::new(options.position_y),
-            position_z: Param::new(options.position_z),
-            orientation_x: Param::new(options.orientation_x),
-            orientation_y: Param::new(options.orientation_y),
-            orientation_z: Param::new(options.orientation_z),
+            position_x: param(options.position_x, 0.),
+            position_y: param(options.position_y, 0.),
+            position_z: param(options.position_z, 0.),
+            orientation_x: param(options.orientation_x, 1.),
+            orientation_y: param(options.orientation_y, 0.),
+            orientation_z: param(options.orientation_z, 0.),
             ref_distance: options.ref_distance,
             max_distance: options.max_distance,
             rolloff_factor: options.rolloff_factor,
@@ -117,19 +173,62 @@ impl PannerNode {
             cone_outer_angle: options.cone_outer_angle,
             cone_outer_gain: options.cone_outer_gain,
             listener_data: None,
+            hrtf_database,
+            hrtf: None,
+            silent_frames: TAIL_FRAMES,
         }
     }
 
-    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
-        let mut changed = self.position_x.update(info, tick);
-        changed |= self.position_y.update(info, tick);
-        changed |= self.position_z.update(info, tick);
-        changed |= self.orientation_x.update(info, tick);
-        changed |= self.orientation_y.update(info, tick);
-        changed |= self.orientation_z.update(info, tick);
+    pub fn update_parameters(&mut self, info: &BlockInfo) -> bool {
+        let mut changed = self.position_x.update_block(info);
+        changed |= self.position_y.update_block(info);
+        changed |= self.position_z.update_block(info);
+        changed |= self.orientation_x.update_block(info);
+        changed |= self.orientation_y.update_block(info);
+        changed |= self.orientation_z.update_block(info);
         changed
     }
 
+    /// Whether the position and orientation are the same for the whole block
+    fn is_constant(&self) -> bool {
+        self.position_x.is_constant()
+            && self.position_y.is_constant()
+            && self.position_z.is_constant()
+            && self.orientation_x.is_constant()
+            && self.orientation_y.is_constant()
+            && self.orientation_z.is_constant()
+    }
+
+    fn position(&self, tick: Tick) -> Vector3D<f32> {
+        Vector3D::new(
+            self.position_x.block_value(tick),
+            self.position_y.block_value(tick),
+            self.position_z.block_value(tick),
+        )
+    }
+
+    fn orientation(&self, tick: Tick) -> Vector3D<f32> {
+        Vector3D::new(
+            self.orientation_x.block_value(tick),
+            self.orientation_y.block_value(tick),
+            self.orientation_z.block_value(tick),
+        )
+    }
+
+    /// Computes the azimuth and elevation of the source, in degrees, and the
+    /// gain from its distance and sound cone, at a tick of the block
+    fn spatialize(
+        &self,
+        tick: Tick,
+        listener: (Vector3D<f32>, Vector3D<f32>, Vector3D<f32>),
+        rolloff_factor: f64,
+    ) -> (f32, f32, f32) {
+        let (azimuth, elevation, dist) = self.azimuth_elevation_distance(tick, listener);
+        let distance_gain = self.distance_gain_fn()(self, dist, rolloff_factor);
+        let gain = distance_gain as f32 * self.cone_gain(tick, listener) as f32;
+        (azimuth, elevation, gain)
+    }
+
     /// Computes azimuth, elevation, and distance of source with respect to a
     /// given AudioListener's position, forward, and up vectors
     /// in degrees
@@ -138,14 +237,11 @@ impl PannerNode {
     /// https://webaudio.github.io/web-audio-api/#Spatialization-distance-effects
     fn azimuth_elevation_distance(
         &self,
+        tick: Tick,
         listener: (Vector3D<f32>, Vector3D<f32>, Vector3D<f32>),
     ) -> (f32, f32, f64) {
         let (listener_position, listener_forward, listener_up) = listener;
-        let source_position = Vector3D::new(
-            self.position_x.value(),
-            self.position_y.value(),
-            self.position_z.value(),
-        );
+        let source_position = self.position(tick);
 
         // degenerate case
         if source_position == listener_position {
@@ -187,23 +283,19 @@ impl PannerNode {
     }
 
     /// https://webaudio.github.io/web-audio-api/#Spatialization-sound-cones
-    fn cone_gain(&self, listener: (Vector3D<f32>, Vector3D<f32>, Vector3D<f32>)) -> f64 {
+    fn cone_gain(
+        &self,
+        tick: Tick,
+        listener: (Vector3D<f32>, Vector3D<f32>, Vector3D<f32>),
+    ) -> f64 {
         let (listener_position, _, _) = listener;
-        let source_position = Vector3D::new(
-            self.position_x.value(),
-            self.position_y.value(),
-            self.position_z.value(),
-        );
-        let source_orientation = Vector3D::new(
-            self.orientation_x.value(),
-            self.orientation_y.value(),
-            self.orientation_z.value(),
-        );
+        let source_position = self.position(tick);
+        let source_orientation = self.orientation(tick);
 
         if source_orientation == Vector3D::zero()
             || (self.cone_inner_angle == 360. && self.cone_outer_angle == 360.)
         {
-            return 0.;
+            return 1.;
         }
 
         let normalized_source_orientation = normalize_zero(source_orientation);
@@ -275,6 +367,12 @@ impl AudioNodeEngine for PannerNode {
     fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
         debug_assert!(inputs.len() == 1);
 
+        if inputs.blocks[0].is_silence() {
+            self.silent_frames += FRAMES_PER_BLOCK_USIZE;
+        } else {
+            self.silent_frames = 0;
+        }
+
         let listener_data = if let Some(listener_data) = self.listener_data.take() {
             listener_data
         } else {
@@ -302,19 +400,49 @@ impl AudioNodeEngine for PannerNode {
                 false
             };
 
-            let distance_gain_fn = self.distance_gain_fn();
+            self.update_parameters(info);
+            // Without any movement, the same spatialization applies to
+            // every frame of the block
+            let constant = self.is_constant() && is_constant(&listener_data);
+            let first = self.spatialize(
+                Tick(0),
+                listener_data.listener_data(Tick(0)),
+                rolloff_factor,
+            );
 
             if self.panning_model == PanningModel::HRTF {
-                unimplemented!()
+                // The direction is taken once per block, at its start,
+                // the gains are still computed for every frame
+                let (azimuth, elevation, gain) = first;
+                let mut gains = [gain; FRAMES_PER_BLOCK_USIZE];
+                if !constant {
+                    for (frame, gain) in gains.iter_mut().enumerate().skip(1) {
+                        let frame = Tick(frame as u64);
+                        let data = listener_data.listener_data(frame);
+                        *gain = self.spatialize(frame, data, rolloff_factor).2;
+                    }
+                }
+
+                let database = &self.hrtf_database;
+                let hrtf = self
+                    .hrtf
+                    .get_or_insert_with(|| Box::new(HrtfPanner::new(database.clone())));
+                let (l, r) = block.data_mut().split_at_mut(FRAMES_PER_BLOCK_USIZE);
+                hrtf.pan(azimuth, elevation, mono, l, r);
+                for ((l, r), gain) in l.iter_mut().zip(r.iter_mut()).zip(gains.iter()) {
+                    *l *= gain;
+                    *r *= gain;
+                }
             } else {
                 let (l, r) = block.data_mut().split_at_mut(FRAMES_PER_BLOCK.0 as usize);
                 for frame in 0..FRAMES_PER_BLOCK.0 {
                     let frame = Tick(frame);
-                    self.update_parameters(info, frame);
-                    let data = listener_data.listener_data(frame);
-                    let (mut azimuth, _elev, dist) = self.azimuth_elevation_distance(data);
-                    let distance_gain = distance_gain_fn(self, dist, rolloff_factor);
-                    let cone_gain = self.cone_gain(data);
+                    let (mut azimuth, _elev, gain) = if constant || frame.0 == 0 {
+                        first
+                    } else {
+                        let data = listener_data.listener_data(frame);
+                        self.spatialize(frame, data, rolloff_factor)
+                    };
 
                     // https://webaudio.github.io/web-audio-api/#Spatialization-equal-power-panning
 
@@ -330,39 +458,10 @@ impl AudioNodeEngine for PannerNode {
                         azimuth = 180. - azimuth;
                     }
 
-                    let x = if mono {
-                        (azimuth + 90.) / 180.
-                    } else if azimuth <= 0. {
-                        (azimuth + 90. / 90.)
-                    } else {
-                        azimuth / 90.
-                    };
-                    let x = x * PI / 2.;
-
-                    let mut gain_l = x.cos();
-                    let mut gain_r = x.sin();
-                    // 9. * PI / 2 is often slightly negative, clamp
-                    if gain_l <= 0. {
-                        gain_l = 0.
-                    }
-                    if gain_r <= 0. {
-                        gain_r = 0.;
-                    }
-
                     let index = frame.0 as usize;
-                    if mono {
-                        let input = l[index];
-                        l[index] = input * gain_l;
-                        r[index] = input * gain_r;
-                    } else if azimuth <= 0. {
-                        l[index] = l[index] + r[index] * gain_l;
-                        r[index] = r[index] * gain_r;
-                    } else {
-                        r[index] = r[index] + l[index] * gain_r;
-                        l[index] = l[index] * gain_l;
-                    }
-                    l[index] = l[index] * distance_gain as f32 * cone_gain as f32;
-                    r[index] = r[index] * distance_gain as f32 * cone_gain as f32;
+                    let (out_l, out_r) = equal_power_pan(azimuth / 90., mono, l[index], r[index]);
+                    l[index] = out_l * gain;
+                    r[index] = out_r * gain;
                 }
             }
         }
@@ -374,6 +473,11 @@ impl AudioNodeEngine for PannerNode {
         1
     }
 
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        // Equal-power panning has no tail time
+        self.panning_model == PanningModel::HRTF && self.silent_frames < TAIL_FRAMES
+    }
+
     fn get_param(&mut self, id: ParamType) -> &mut Param {
         match id {
             ParamType::Position(ParamDir::X) => &mut self.position_x,
@@ -1,7 +1,9 @@
 use block::Block;
 use block::Tick;
-use block::FRAMES_PER_BLOCK_USIZE;
+use block::FRAMES_PER_BLOCK;
 use node::BlockInfo;
+use std::cmp::Ordering::{Greater, Less};
+use std::f32;
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub enum ParamType {
@@ -10,10 +12,21 @@ pub enum ParamType {
     Gain,
     Q,
     PlaybackRate,
+    Offset,
+    DelayTime,
+    Threshold,
+    Knee,
+    Ratio,
+    Attack,
+    Release,
+    Pan,
     Position(ParamDir),
     Forward(ParamDir),
     Up(ParamDir),
     Orientation(ParamDir),
+    /// A param declared by an AudioWorkletNode, by the index of its
+    /// descriptor
+    Custom(u32),
 }
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -23,16 +36,86 @@ pub enum ParamDir {
     Z,
 }
 
+/// The nominal range, default value and automation rate of a Param,
+/// for the control thread to report
+///
+/// https://webaudio.github.io/web-audio-api/#AudioParam
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamDescriptor {
+    pub default_value: f32,
+    pub min_value: f32,
+    pub max_value: f32,
+    pub automation_rate: ParamRate,
+    /// Whether the automation rate can't be changed
+    pub fixed_automation_rate: bool,
+}
+
+impl ParamDescriptor {
+    /// An a-rate param, which may be made k-rate
+    pub fn new(default_value: f32, min_value: f32, max_value: f32) -> Self {
+        debug_assert!(min_value <= max_value);
+        ParamDescriptor {
+            default_value,
+            min_value,
+            max_value,
+            automation_rate: ParamRate::ARate,
+            fixed_automation_rate: false,
+        }
+    }
+
+    /// An a-rate param with the largest possible nominal range
+    pub fn unbounded(default_value: f32) -> Self {
+        Self::new(default_value, f32::MIN, f32::MAX)
+    }
+
+    /// A detune param, in cents, whose range is limited so that detuned
+    /// frequencies don't overflow
+    pub fn detune() -> Self {
+        let max = 1200. * f32::MAX.log2();
+        Self::new(0., -max, max)
+    }
+
+    /// A param which is always k-rate
+    pub fn fixed_k_rate(default_value: f32, min_value: f32, max_value: f32) -> Self {
+        ParamDescriptor {
+            automation_rate: ParamRate::KRate,
+            fixed_automation_rate: true,
+            ..Self::new(default_value, min_value, max_value)
+        }
+    }
+
+    /// Clamp a value to the nominal range
+    pub fn clamp(&self, val: f32) -> f32 {
+        if val < self.min_value {
+            self.min_value
+        } else if val > self.max_value {
+            self.max_value
+        } else {
+            val
+        }
+    }
+}
+
 /// An AudioParam.
 ///
 /// https://webaudio.github.io/web-audio-api/#AudioParam
 pub struct Param {
     val: f32,
+    descriptor: ParamDescriptor,
+    /// The value of the param before the first automation event, which is
+    /// the last value set directly or held by events that were dropped
+    initial_value: f32,
     kind: ParamRate,
+    /// The automation events, ordered by time. Events with the same time
+    /// are in insertion order.
     events: Vec<AutomationEvent>,
+    /// The value of the param at the time of each event, just before it
+    /// takes effect
+    start_values: Vec<f32>,
+    /// The number of events whose time has been reached
     current_event: usize,
-    event_start_time: Tick,
-    event_start_value: f32,
+    /// The last tick the value was computed for
+    current_tick: Tick,
     /// Cache of inputs from connect()ed nodes
     blocks: Vec<Block>,
     /// The value of all connect()ed inputs mixed together, for this frame
@@ -40,6 +123,9 @@ pub struct Param {
     /// If true, `blocks` has been summed together into a single block
     summed: bool,
     dirty: bool,
+    /// The values computed by `update_block`, one per frame, or a single
+    /// one if the value is constant across the block
+    block_values: Vec<f32>,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -51,18 +137,21 @@ pub enum ParamRate {
 }
 
 impl Param {
-    pub fn new(val: f32) -> Self {
+    pub fn new(val: f32, descriptor: ParamDescriptor) -> Self {
         Param {
             val,
-            kind: ParamRate::ARate,
+            descriptor,
+            initial_value: val,
+            kind: descriptor.automation_rate,
             events: vec![],
+            start_values: vec![],
             current_event: 0,
-            event_start_time: Tick(0),
-            event_start_value: val,
+            current_tick: Tick(0),
             blocks: Vec::new(),
             block_mix_val: 0.,
             summed: false,
             dirty: false,
+            block_values: vec![val],
         }
     }
 
@@ -102,115 +191,330 @@ impl Param {
             false
         };
 
-        if self.events.len() <= self.current_event {
+        // Nothing to do if the value computed for the previous tick is final
+        let settled = self.is_settled();
+        self.current_tick = block.absolute_tick(tick);
+        if settled {
             return changed;
         }
 
-        let current_tick = block.absolute_tick(tick);
-        let mut current_event = &self.events[self.current_event];
-
-        // move to next event if necessary
-        // XXXManishearth k-rate events may get skipped over completely by this
-        // method. Firefox currently doesn't support these, however, so we can
-        // handle those later
-        loop {
-            let mut move_next = false;
-            if let Some(done_time) = current_event.done_time() {
-                // If this event is done, move on
-                if done_time < current_tick {
-                    move_next = true;
-                }
-            } else if let Some(next) = self.events.get(self.current_event + 1) {
-                // this event has no done time and we must run it till the next one
-                // starts
-                if let Some(start_time) = next.start_time() {
-                    // if the next one is ready to start, move on
-                    if start_time <= current_tick {
-                        move_next = true;
-                    }
-                } else {
-                    // If we have a next event with no start time and
-                    // the current event has no done time, this *has* to be because
-                    // the current event is SetTargetAtTime and the next is a Ramp
-                    // event. In this case we skip directly to the ramp assuming
-                    // the SetTarget is ready to start (or has started already)
-                    if current_event.time() <= current_tick {
-                        move_next = true;
-                    } else {
-                        // This is a SetTarget event before its start time, ignore
-                        return changed;
-                    }
-                }
-            }
-            if move_next {
-                self.current_event += 1;
-                self.event_start_value = self.val;
-                self.event_start_time = current_tick;
-                if let Some(next) = self.events.get(self.current_event + 1) {
-                    current_event = next;
-                    // may need to move multiple times
-                    continue;
-                } else {
-                    return changed;
-                }
+        // The value is computed from the timeline as of the current tick,
+        // so k-rate params don't miss the events that happened between
+        // the starts of two blocks
+        let current_tick = self.current_tick;
+        let passed = self.events_until(current_tick);
+        if passed != self.current_event {
+            self.current_event = passed;
+            if self.is_settled() && passed > 1 {
+                // Only the last event matters from now on, in case a ramp
+                // is added after it
+                self.initial_value = self.value_at(passed, current_tick);
+                self.events.drain(..passed - 1);
+                self.start_values.drain(..passed - 1);
+                self.current_event = 1;
             }
-            break;
         }
+        let val = self.value_at(self.current_event, current_tick);
+        if val != self.val {
+            self.val = val;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Update the value of this param for every tick of a block at once,
+    /// to be read with `block_value` or `block_values` afterwards
+    ///
+    /// This should be called instead of `update`, once per block.
+    ///
+    /// Returns true if anything changed
+    pub fn update_block(&mut self, block: &BlockInfo) -> bool {
+        self.block_values.clear();
+        let mut changed = self.update(block, Tick(0));
+        let first = self.value();
+        self.block_values.push(first);
+
+        // Without inputs, the value of a settled timeline is the same for
+        // the whole block, so there's no need to go through every tick
+        if self.kind == ParamRate::KRate || (self.is_settled() && self.blocks.is_empty()) {
+            self.current_tick = block.absolute_tick(FRAMES_PER_BLOCK - Tick(1));
+            return changed;
+        }
+
+        let mut constant = true;
+        for tick in 1..FRAMES_PER_BLOCK.0 {
+            changed |= self.update(block, Tick(tick));
+            let val = self.value();
+            constant &= val == first;
+            self.block_values.push(val);
+        }
+        if constant {
+            self.block_values.truncate(1);
+        }
+        changed
+    }
+
+    /// Whether the values computed by the last `update_block` are the same
+    /// for the whole block
+    pub fn is_constant(&self) -> bool {
+        self.block_values.len() == 1
+    }
+
+    /// The values computed by the last `update_block`: one per tick, or a
+    /// single one if `is_constant()`
+    pub fn block_values(&self) -> &[f32] {
+        &self.block_values
+    }
 
-        current_event.run(
-            &mut self.val,
-            current_tick,
-            self.event_start_time,
-            self.event_start_value,
-        )
+    /// The value computed by the last `update_block` for a tick of the block
+    pub fn block_value(&self, tick: Tick) -> f32 {
+        if self.is_constant() {
+            self.block_values[0]
+        } else {
+            self.block_values[tick.0 as usize]
+        }
     }
 
     pub fn value(&self) -> f32 {
         // the data from connect()ed audionodes is first mixed
         // together in update(), and then mixed with the actual param value
         // https://webaudio.github.io/web-audio-api/#dom-audionode-connect-destinationparam-output
-        self.val + self.block_mix_val
+        // https://webaudio.github.io/web-audio-api/#computation-of-value
+        self.descriptor.clamp(self.val + self.block_mix_val)
     }
 
-    pub fn set_rate(&mut self, rate: ParamRate) {
+    pub fn descriptor(&self) -> ParamDescriptor {
+        self.descriptor
+    }
+
+    pub fn set_rate(&mut self, rate: ParamRate) -> Result<(), AutomationError> {
+        if self.descriptor.fixed_automation_rate && rate != self.kind {
+            return Err(AutomationError::FixedRate);
+        }
         self.kind = rate;
+        Ok(())
     }
 
-    pub(crate) fn insert_event(&mut self, event: AutomationEvent) {
-        if let AutomationEvent::SetValue(val) = event {
-            self.val = val;
-            self.event_start_value = val;
-            self.dirty = true;
-            return;
+    /// Insert an event in the timeline, following
+    /// https://webaudio.github.io/web-audio-api/#dfn-automation-event
+    pub(crate) fn insert_event(&mut self, event: AutomationEvent) -> Result<(), AutomationError> {
+        let event = match event {
+            // Setting the value is the same as setting it at the current time
+            // https://webaudio.github.io/web-audio-api/#dom-audioparam-value
+            AutomationEvent::SetValue(val) => {
+                AutomationEvent::SetValueAtTime(val, self.current_tick)
+            }
+            event => event,
+        };
+
+        if let AutomationEvent::SetValueCurveAtTime(ref values, _, duration, _) = event {
+            if values.len() < 2 || duration.0 == 0 {
+                return Err(AutomationError::InvalidCurve);
+            }
         }
 
         let time = event.time();
+        match event {
+            AutomationEvent::CancelScheduledValues(..) => self.cancel_scheduled_values(time),
+            AutomationEvent::CancelAndHoldAtTime(..) => self.cancel_and_hold(time),
+            event => {
+                if self.events.iter().any(|e| e.overlaps(&event)) {
+                    return Err(AutomationError::Overlap);
+                }
+                // Events go after the ones with the same time
+                let idx = self.events_until(time);
+                match event {
+                    AutomationEvent::SetValueAtTime(val, _) if time <= self.current_tick => {
+                        // Nothing before this event matters anymore, the
+                        // value is held even if it gets cancelled
+                        self.initial_value = val;
+                        self.events.drain(..idx);
+                        self.events.insert(0, event);
+                    }
+                    AutomationEvent::RampToValueAtTime(..)
+                        if idx == 0 && time > self.current_tick =>
+                    {
+                        // Without a previous event, the ramp starts from the
+                        // current value at the current time
+                        self.events.insert(
+                            0,
+                            AutomationEvent::SetValueAtTime(self.val, self.current_tick),
+                        );
+                        self.events.insert(1, event);
+                    }
+                    AutomationEvent::RampToValueAtTime(..)
+                        if time > self.current_tick && self.target_started(idx) =>
+                    {
+                        // After a SetTarget which already started, the ramp
+                        // starts from its value at the current time
+                        // https://webaudio.github.io/web-audio-api/#dom-audioparam-linearramptovalueattime
+                        let value = self.event_value(idx - 1, self.current_tick);
+                        self.events.insert(
+                            idx,
+                            AutomationEvent::SetValueAtTime(value, self.current_tick),
+                        );
+                        self.events.insert(idx + 1, event);
+                    }
+                    event => self.events.insert(idx, event),
+                }
+            }
+        }
+        self.timeline_changed();
+        Ok(())
+    }
 
-        let result = self.events.binary_search_by(|e| e.time().cmp(&time));
-        // XXXManishearth this should handle overlapping events
-        let idx = match result {
-            Ok(idx) => idx,
-            Err(idx) => idx,
+    /// Whether the event before `idx` is a SetTarget which started before
+    /// the current time
+    fn target_started(&self, idx: usize) -> bool {
+        match self.events[..idx].last() {
+            Some(&AutomationEvent::SetTargetAtTime(_, start, _)) => start < self.current_tick,
+            _ => false,
+        }
+    }
+
+    /// https://webaudio.github.io/web-audio-api/#dom-audioparam-cancelscheduledvalues
+    fn cancel_scheduled_values(&mut self, time: Tick) {
+        let mut idx = self.events_before(time);
+        // A curve running at the cancel time is cancelled as well
+        if let Some(&AutomationEvent::SetValueCurveAtTime(_, start, duration, _)) =
+            self.events[..idx].last()
+        {
+            if time < start + duration {
+                idx -= 1;
+            }
+        }
+        self.events.truncate(idx);
+    }
+
+    /// https://webaudio.github.io/web-audio-api/#dom-audioparam-cancelandholdattime
+    fn cancel_and_hold(&mut self, time: Tick) {
+        let idx = self.events_until(time);
+        let held = self.value_at(idx, time);
+        let ramp = match self.events.get(idx) {
+            Some(&AutomationEvent::RampToValueAtTime(kind, ..))
+                if idx > 0 && self.ramp_start(idx - 1).0 <= time =>
+            {
+                Some(kind)
+            }
+            _ => None,
+        };
+        // Whether the previous event would keep changing the value
+        let running = match self.events[..idx].last() {
+            Some(&AutomationEvent::SetTargetAtTime(..)) => true,
+            Some(&AutomationEvent::SetValueCurveAtTime(_, start, duration, _)) => {
+                time < start + duration
+            }
+            _ => false,
         };
+        self.events.truncate(idx);
+        // A curve running at the cancel time ends there
+        if let Some(&mut AutomationEvent::SetValueCurveAtTime(_, start, ref mut duration, _)) =
+            self.events.last_mut()
+        {
+            if time < start + *duration {
+                *duration = time - start;
+            }
+        }
+        if let Some(kind) = ramp {
+            // The ramp is cut short, ending at the value it has at the
+            // cancel time
+            self.events
+                .push(AutomationEvent::RampToValueAtTime(kind, held, time));
+        } else if running {
+            self.events
+                .push(AutomationEvent::SetValueAtTime(held, time));
+        }
+    }
+
+    /// Recompute everything that depends on the events, after they were
+    /// modified
+    fn timeline_changed(&mut self) {
+        self.start_values.clear();
+        for i in 0..self.events.len() {
+            let value = self.value_at(i, self.events[i].time());
+            self.start_values.push(value);
+        }
+        // Events may have been added or removed before the current time
+        self.current_event = self.events_until(self.current_tick);
+        self.val = self.value_at(self.current_event, self.current_tick);
+        self.dirty = true;
+    }
+
+    /// The number of events whose time is `tick` or earlier
+    fn events_until(&self, tick: Tick) -> usize {
+        self.events
+            .binary_search_by(|e| if e.time() <= tick { Less } else { Greater })
+            .unwrap_err()
+    }
+
+    /// The number of events whose time is before `tick`
+    fn events_before(&self, tick: Tick) -> usize {
+        self.events
+            .binary_search_by(|e| if e.time() < tick { Less } else { Greater })
+            .unwrap_err()
+    }
 
-        // XXXManishearth this isn't quite correct, this
-        // doesn't handle cases for when this lands inside a running
-        // event
-        if let Some(is_hold) = event.cancel_event() {
-            self.events.truncate(idx);
-            if !is_hold {
-                // If we cancelled the current event, reset
-                // the value to what it was before
-                if self.current_event >= self.events.len() {
-                    self.val = self.event_start_value;
+    /// Whether the timeline won't change the value anymore, after the one
+    /// computed for the current tick
+    fn is_settled(&self) -> bool {
+        if self.current_event < self.events.len() {
+            return false;
+        }
+        match self.events.last() {
+            Some(&AutomationEvent::SetTargetAtTime(..)) => false,
+            Some(&AutomationEvent::SetValueCurveAtTime(_, start, duration, _)) => {
+                self.current_tick >= start + duration
+            }
+            _ => true,
+        }
+    }
+
+    /// The value of the timeline at `tick`, given the number of events
+    /// whose time is `tick` or earlier
+    fn value_at(&self, passed: usize, tick: Tick) -> f32 {
+        if passed == 0 {
+            return self.initial_value;
+        }
+        if let Some(&AutomationEvent::RampToValueAtTime(kind, value, end)) = self.events.get(passed)
+        {
+            let (start, start_value) = self.ramp_start(passed - 1);
+            if start <= tick {
+                return ramp_value(kind, start, start_value, end, value, tick);
+            }
+        }
+        self.event_value(passed - 1, tick)
+    }
+
+    /// The time and value a ramp following the given event starts from
+    fn ramp_start(&self, idx: usize) -> (Tick, f32) {
+        let time = match self.events[idx] {
+            AutomationEvent::SetValueCurveAtTime(_, start, duration, _) => start + duration,
+            ref event => event.time(),
+        };
+        (time, self.event_value(idx, time))
+    }
+
+    /// The value of the given event at `tick`, which can't be before its time
+    fn event_value(&self, idx: usize, tick: Tick) -> f32 {
+        match self.events[idx] {
+            AutomationEvent::SetValueAtTime(val, _) => val,
+            AutomationEvent::RampToValueAtTime(_, val, _) => val,
+            AutomationEvent::SetTargetAtTime(val, start, tau) => {
+                if tau == 0. {
+                    return val;
                 }
-                // don't actually insert the event
-                return;
+                let exp = -((tick - start) / tau);
+                val + (self.start_values[idx] - val) * exp.exp() as f32
+            }
+            AutomationEvent::SetValueCurveAtTime(ref values, start, duration, values_duration) => {
+                curve_value(values, start, values_duration, tick.min(start + duration))
+            }
+            AutomationEvent::SetValue(..)
+            | AutomationEvent::CancelAndHoldAtTime(..)
+            | AutomationEvent::CancelScheduledValues(..) => {
+                unreachable!("SetValue and cancel events should never appear in the timeline")
             }
         }
-        self.events.insert(idx, event);
-        // XXXManishearth handle inserting events with a time before that
-        // of the current one
     }
 
     pub(crate) fn add_block(&mut self, block: Block) {
@@ -231,20 +535,18 @@ impl Param {
     ///
     /// Invariant: `block` must be a FRAMES_PER_BLOCK length array filled with silence
     pub(crate) fn flush_to_block(&mut self, info: &BlockInfo, block: &mut [f32]) {
-        // common case
-        if self.current_event >= self.events.len() && self.blocks.is_empty() {
-            if self.val != 0. {
-                for tick in 0..(FRAMES_PER_BLOCK_USIZE) {
+        self.update_block(info);
+        if self.is_constant() {
+            let val = self.block_values[0];
+            // if the value is zero, our buffer is already zeroed
+            if val != 0. {
+                for sample in block.iter_mut() {
                     // ideally this can use some kind of vectorized memset()
-                    block[tick] = self.val;
+                    *sample = val;
                 }
             }
-        // if the value is zero, our buffer is already zeroed
         } else {
-            for tick in 0..(FRAMES_PER_BLOCK_USIZE) {
-                self.update(info, Tick(tick as u64));
-                block[tick] = self.val;
-            }
+            block.copy_from_slice(&self.block_values);
         }
     }
 }
@@ -255,25 +557,48 @@ pub enum RampKind {
     Exponential,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+/// Why an automation event could not be added to the timeline of a Param
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AutomationError {
+    /// The event overlaps a SetValueCurveAtTime event
+    ///
+    /// https://webaudio.github.io/web-audio-api/#dom-audioparam-setvaluecurveattime
+    Overlap,
+    /// A curve needs at least two values and a non-zero duration
+    InvalidCurve,
+    /// The automation rate of the param can't be changed
+    ///
+    /// https://webaudio.github.io/web-audio-api/#dom-audioparam-automationrate
+    FixedRate,
+}
+
+#[derive(Clone, PartialEq, Debug)]
 /// https://webaudio.github.io/web-audio-api/#dfn-automation-event
 pub(crate) enum AutomationEvent {
     SetValue(f32),
     SetValueAtTime(f32, Tick),
     RampToValueAtTime(RampKind, f32, Tick),
     SetTargetAtTime(f32, Tick, /* time constant, units of Tick */ f64),
+    /// The curve may end before all of its values are used, if it was
+    /// cancelled while running
+    SetValueCurveAtTime(
+        Vec<f32>,
+        Tick,
+        /* duration */ Tick,
+        /* duration the values are spread over */ Tick,
+    ),
     CancelAndHoldAtTime(Tick),
     CancelScheduledValues(Tick),
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 /// An AutomationEvent that uses times in s instead of Ticks
 pub enum UserAutomationEvent {
     SetValue(f32),
     SetValueAtTime(f32, /* time */ f64),
     RampToValueAtTime(RampKind, f32, /* time */ f64),
     SetTargetAtTime(f32, f64, /* time constant, units of s */ f64),
-    // SetValueCurveAtTime(Vec<f32>, Tick, /* duration */ Tick)
+    SetValueCurveAtTime(Vec<f32>, /* start time */ f64, /* duration */ f64),
     CancelAndHoldAtTime(f64),
     CancelScheduledValues(f64),
 }
@@ -295,6 +620,15 @@ impl UserAutomationEvent {
                     tau * rate as f64,
                 )
             }
+            UserAutomationEvent::SetValueCurveAtTime(values, start, duration) => {
+                let duration = Tick::from_time(duration, rate);
+                AutomationEvent::SetValueCurveAtTime(
+                    values,
+                    Tick::from_time(start, rate),
+                    duration,
+                    duration,
+                )
+            }
             UserAutomationEvent::CancelScheduledValues(t) => {
                 AutomationEvent::CancelScheduledValues(Tick::from_time(t, rate))
             }
@@ -312,105 +646,141 @@ impl AutomationEvent {
             AutomationEvent::SetValueAtTime(_, tick) => tick,
             AutomationEvent::RampToValueAtTime(_, _, tick) => tick,
             AutomationEvent::SetTargetAtTime(_, start, _) => start,
+            AutomationEvent::SetValueCurveAtTime(_, start, ..) => start,
             AutomationEvent::CancelAndHoldAtTime(t) => t,
-            AutomationEvent::CancelScheduledValues(..) | AutomationEvent::SetValue(..) => {
-                unreachable!("CancelScheduledValues/SetValue should never appear in the timeline")
+            AutomationEvent::CancelScheduledValues(t) => t,
+            AutomationEvent::SetValue(..) => {
+                unreachable!("SetValue should never appear in the timeline")
             }
         }
     }
 
-    pub fn done_time(&self) -> Option<Tick> {
-        match *self {
-            AutomationEvent::SetValueAtTime(_, tick) => Some(tick),
-            AutomationEvent::RampToValueAtTime(_, _, tick) => Some(tick),
-            AutomationEvent::SetTargetAtTime(..) => None,
-            AutomationEvent::CancelAndHoldAtTime(t) => Some(t),
-            AutomationEvent::CancelScheduledValues(..) | AutomationEvent::SetValue(..) => {
-                unreachable!("CancelScheduledValues/SetValue should never appear in the timeline")
+    /// Whether `new` can't be added to a timeline containing this event,
+    /// because one of them is a curve and they overlap
+    fn overlaps(&self, new: &AutomationEvent) -> bool {
+        match (self, new) {
+            (
+                &AutomationEvent::SetValueCurveAtTime(_, start, duration, _),
+                &AutomationEvent::SetValueCurveAtTime(_, new_start, new_duration, _),
+            ) => start < new_start + new_duration && new_start < start + duration,
+            (&AutomationEvent::SetValueCurveAtTime(_, start, duration, _), _) => {
+                new.time() >= start && new.time() < start + duration
+            }
+            (_, &AutomationEvent::SetValueCurveAtTime(_, start, duration, _)) => {
+                self.time() > start && self.time() < start + duration
             }
+            _ => false,
         }
     }
+}
 
-    pub fn start_time(&self) -> Option<Tick> {
-        match *self {
-            AutomationEvent::SetValueAtTime(_, tick) => Some(tick),
-            AutomationEvent::RampToValueAtTime(..) => None,
-            AutomationEvent::SetTargetAtTime(_, start, _) => Some(start),
-            AutomationEvent::CancelAndHoldAtTime(t) => Some(t),
-            AutomationEvent::CancelScheduledValues(..) | AutomationEvent::SetValue(..) => {
-                unreachable!("CancelScheduledValues/SetValue should never appear in the timeline")
+/// The value of a ramp from `start_value` at `start` to `end_value` at
+/// `end`, at `tick` which is between the two
+///
+/// https://webaudio.github.io/web-audio-api/#dom-audioparam-linearramptovalueattime
+/// https://webaudio.github.io/web-audio-api/#dom-audioparam-exponentialramptovalueattime
+fn ramp_value(
+    kind: RampKind,
+    start: Tick,
+    start_value: f32,
+    end: Tick,
+    end_value: f32,
+    tick: Tick,
+) -> f32 {
+    let progress = (tick - start).0 as f32 / (end - start).0 as f32;
+    match kind {
+        RampKind::Linear => start_value + (end_value - start_value) * progress,
+        RampKind::Exponential => {
+            let ratio = end_value / start_value;
+            if start_value == 0. || ratio < 0. {
+                start_value
+            } else {
+                start_value * ratio.powf(progress)
             }
         }
     }
+}
 
-    /// Returns Some if it's a cancel event
-    /// the boolean is if it's CancelAndHold
-    pub fn cancel_event(&self) -> Option<bool> {
-        match *self {
-            AutomationEvent::CancelAndHoldAtTime(..) => Some(true),
-            AutomationEvent::CancelScheduledValues(..) => Some(false),
-            _ => None,
-        }
+/// The value of a SetValueCurveAtTime event at `tick`, linearly
+/// interpolated between the points of the curve, and holding the last
+/// point once the curve is over
+///
+/// https://webaudio.github.io/web-audio-api/#dom-audioparam-setvaluecurveattime
+fn curve_value(values: &[f32], start: Tick, duration: Tick, tick: Tick) -> f32 {
+    let last = values.len() - 1;
+    if tick >= start + duration {
+        return values[last];
     }
+    let position = (tick - start).0 as f64 * last as f64 / duration.0 as f64;
+    let k = position.floor() as usize;
+    if k >= last {
+        return values[last];
+    }
+    let fraction = (position - k as f64) as f32;
+    values[k] + (values[k + 1] - values[k]) * fraction
+}
 
-    /// Update a parameter based on this event
-    ///
-    /// Returns true if something changed
-    pub fn run(
-        &self,
-        value: &mut f32,
-        current_tick: Tick,
-        event_start_time: Tick,
-        event_start_value: f32,
-    ) -> bool {
-        if let Some(start_time) = self.start_time() {
-            if start_time > current_tick {
-                // The previous event finished and we advanced to this
-                // event, but it's not started yet. Return early
-                return false;
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        match *self {
-            AutomationEvent::SetValueAtTime(val, time) => {
-                if current_tick == time {
-                    *value = val;
-                    true
-                } else {
-                    false
-                }
-            }
-            AutomationEvent::RampToValueAtTime(kind, val, time) => {
-                let progress =
-                    (current_tick - event_start_time).0 as f32 / (time - event_start_time).0 as f32;
-                match kind {
-                    RampKind::Linear => {
-                        *value = event_start_value + (val - event_start_value) * progress;
-                    }
-                    RampKind::Exponential => {
-                        let ratio = val / event_start_value;
-                        if event_start_value == 0. || ratio < 0. {
-                            if time == current_tick {
-                                *value = val;
-                            } else {
-                                *value = event_start_value;
-                            }
-                        } else {
-                            *value = event_start_value * (ratio).powf(progress);
-                        }
-                    }
-                }
-                true
-            }
-            AutomationEvent::SetTargetAtTime(val, start, tau) => {
-                let exp = -((current_tick - start) / tau);
-                *value = val + (event_start_value - val) * exp.exp() as f32;
-                true
-            }
-            AutomationEvent::CancelAndHoldAtTime(..) => false,
-            AutomationEvent::CancelScheduledValues(..) | AutomationEvent::SetValue(..) => {
-                unreachable!("CancelScheduledValues/SetValue should never appear in the timeline")
-            }
+    fn block_info(frame: u64) -> BlockInfo {
+        BlockInfo {
+            sample_rate: 48000.,
+            frame: Tick(frame),
+            time: frame as f64 / 48000.,
         }
     }
+
+    #[test]
+    fn ramp_after_started_target_is_continuous() {
+        let mut param = Param::new(0., ParamDescriptor::unbounded(0.));
+        param
+            .insert_event(AutomationEvent::SetTargetAtTime(1., Tick(0), 128.))
+            .unwrap();
+        param.update_block(&block_info(0));
+        param.update_block(&block_info(128));
+        let before = param.block_value(Tick(127));
+        assert!(before > 0.8);
+
+        param
+            .insert_event(AutomationEvent::RampToValueAtTime(
+                RampKind::Linear,
+                0.,
+                Tick(512),
+            ))
+            .unwrap();
+        param.update_block(&block_info(256));
+        let after = param.block_value(Tick(0));
+        assert!((after - before).abs() < 0.01);
+        assert!(param.block_value(Tick(127)) < after);
+    }
+
+    #[test]
+    fn cancel_and_hold_ends_curve() {
+        let mut param = Param::new(0., ParamDescriptor::unbounded(0.));
+        param
+            .insert_event(AutomationEvent::SetValueCurveAtTime(
+                vec![0., 1.],
+                Tick(0),
+                Tick(1024),
+                Tick(1024),
+            ))
+            .unwrap();
+        param
+            .insert_event(AutomationEvent::CancelAndHoldAtTime(Tick(256)))
+            .unwrap();
+        // The curve doesn't span the rest of its former duration anymore
+        param
+            .insert_event(AutomationEvent::SetValueAtTime(2., Tick(512)))
+            .unwrap();
+
+        let values: Vec<f32> = (0..5)
+            .map(|block| {
+                param.update_block(&block_info(block * 128));
+                param.block_value(Tick(0))
+            })
+            .collect();
+        assert_eq!(values, [0., 0.125, 0.25, 0.25, 2.]);
+    }
 }
@@ -1,18 +1,30 @@
 use analyser_node::AnalyserNode;
+use audio_worklet_node::AudioWorkletNode;
 use biquad_filter_node::BiquadFilterNode;
 use block::{Chunk, Tick, FRAMES_PER_BLOCK};
 use buffer_source_node::AudioBufferSourceNode;
 use channel_node::{ChannelMergerNode, ChannelSplitterNode};
+use constant_source_node::ConstantSourceNode;
 use context::{AudioContextOptions, ProcessingState, StateChangeResult};
+use convolver_node::ConvolverNode;
+use delay_node::DelayNode;
+use dynamics_compressor_node::DynamicsCompressorNode;
 use gain_node::GainNode;
 use graph::{AudioGraph, InputPort, NodeId, OutputPort, PortId};
+use hrtf::HrtfDatabase;
+use iir_filter_node::IIRFilterNode;
+use media_element_source_node::MediaElementSourceNode;
+use media_stream_destination_node::MediaStreamDestinationNode;
 use node::{AudioNodeEngine, AudioNodeInit, AudioNodeMessage};
 use node::{BlockInfo, ChannelInfo};
 use offline_sink::OfflineAudioSink;
-use oscillator_node::OscillatorNode;
+use oscillator_node::{BuiltinPeriodicWaves, OscillatorNode};
 use panner_node::PannerNode;
 use sink::{AudioSink, DummyAudioSink};
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
+use stereo_panner_node::StereoPannerNode;
+use wave_shaper_node::WaveShaperNode;
 
 pub enum AudioRenderThreadMsg {
     CreateNode(AudioNodeInit, Sender<NodeId>, ChannelInfo),
@@ -31,6 +43,9 @@ pub enum AudioRenderThreadMsg {
     DisconnectOutputBetween(PortId<OutputPort>, NodeId),
     DisconnectOutputBetweenTo(PortId<OutputPort>, PortId<InputPort>),
 
+    RemoveNode(NodeId),
+    ReleaseNode(NodeId),
+
     SetSinkEosCallback(Box<Fn(Box<AsRef<[f32]>>) + Send + Sync + 'static>),
 }
 
@@ -96,6 +111,10 @@ pub struct AudioRenderThread<S: AudioSink> {
     pub sample_rate: f32,
     pub current_time: f64,
     pub current_frame: Tick,
+    /// Shared by all oscillators, computed when the first one is created
+    builtin_periodic_waves: Option<Arc<BuiltinPeriodicWaves>>,
+    /// Shared by all panners, computed when the first one is created
+    hrtf_database: Option<Arc<HrtfDatabase>>,
 }
 
 impl<S: AudioSink + 'static> AudioRenderThread<S> {
@@ -137,6 +156,8 @@ impl<S: AudioSink + 'static> AudioRenderThread<S> {
             sample_rate,
             current_time: 0.,
             current_frame: Tick(0),
+            builtin_periodic_waves: None,
+            hrtf_database: None,
         })
     }
 
@@ -183,22 +204,67 @@ impl<S: AudioSink + 'static> AudioRenderThread<S> {
         let mut needs_listener = false;
         let node: Box<AudioNodeEngine> = match node_type {
             AudioNodeInit::AnalyserNode(sender) => Box::new(AnalyserNode::new(sender, ch)),
+            AudioNodeInit::AnalyserEngineNode(options) => {
+                Box::new(AnalyserNode::new_with_engine(options, ch))
+            }
             AudioNodeInit::AudioBufferSourceNode(options) => {
                 Box::new(AudioBufferSourceNode::new(options, ch))
             }
+            AudioNodeInit::AudioWorkletNode(options) => {
+                Box::new(AudioWorkletNode::new(options, ch))
+            }
             AudioNodeInit::BiquadFilterNode(options) => {
                 Box::new(BiquadFilterNode::new(options, ch, self.sample_rate))
             }
+            AudioNodeInit::ConvolverNode(options) => {
+                Box::new(ConvolverNode::new(options, ch, self.sample_rate))
+            }
+            AudioNodeInit::DelayNode(options) => {
+                Box::new(DelayNode::new(options, ch, self.sample_rate))
+            }
+            AudioNodeInit::DynamicsCompressionNode(options) => {
+                Box::new(DynamicsCompressorNode::new(options, ch))
+            }
             AudioNodeInit::GainNode(options) => Box::new(GainNode::new(options, ch)),
+            AudioNodeInit::IIRFilterNode(options) => Box::new(IIRFilterNode::new(options, ch)),
+            AudioNodeInit::MediaElementSourceNode(options) => {
+                Box::new(MediaElementSourceNode::new(options, ch))
+            }
+            AudioNodeInit::MediaStreamDestinationNode(options) => {
+                Box::new(MediaStreamDestinationNode::new(options, ch))
+            }
             AudioNodeInit::PannerNode(options) => {
                 needs_listener = true;
-                Box::new(PannerNode::new(options, ch))
+                let sample_rate = self.sample_rate;
+                let hrtf_database = self
+                    .hrtf_database
+                    .get_or_insert_with(|| Arc::new(HrtfDatabase::new(sample_rate)))
+                    .clone();
+                Box::new(PannerNode::new(options, ch, hrtf_database))
+            }
+            AudioNodeInit::OscillatorNode(options) => {
+                let builtin_periodic_waves = self
+                    .builtin_periodic_waves
+                    .get_or_insert_with(|| Arc::new(BuiltinPeriodicWaves::new()))
+                    .clone();
+                Box::new(OscillatorNode::new(
+                    options,
+                    ch,
+                    self.sample_rate,
+                    builtin_periodic_waves,
+                ))
             }
-            AudioNodeInit::OscillatorNode(options) => Box::new(OscillatorNode::new(options, ch)),
             AudioNodeInit::ChannelMergerNode(options) => {
                 Box::new(ChannelMergerNode::new(options, ch))
             }
             AudioNodeInit::ChannelSplitterNode => Box::new(ChannelSplitterNode::new(ch)),
+            AudioNodeInit::ConstantSourceNode(options) => {
+                Box::new(ConstantSourceNode::new(options, ch))
+            }
+            AudioNodeInit::StereoPannerNode(options) => {
+                Box::new(StereoPannerNode::new(options, ch))
+            }
+            AudioNodeInit::WaveShaperNode(options) => Box::new(WaveShaperNode::new(options, ch)),
             _ => unimplemented!(),
         };
         let id = self.graph.add_node(node);
@@ -247,7 +313,9 @@ impl<S: AudioSink + 'static> AudioRenderThread<S> {
                     response.send(context.current_time).unwrap()
                 }
                 AudioRenderThreadMsg::MessageNode(id, msg) => {
-                    context.graph.node_mut(id).message(msg, sample_rate)
+                    if context.graph.has_node(id) {
+                        context.graph.node_mut(id).message(msg, sample_rate)
+                    }
                 }
                 AudioRenderThreadMsg::SinkNeedData => {
                     // Do nothing. This will simply unblock the thread so we
@@ -269,6 +337,8 @@ impl<S: AudioSink + 'static> AudioRenderThread<S> {
                 AudioRenderThreadMsg::DisconnectOutputBetweenTo(from, to) => {
                     context.graph.disconnect_output_between_to(from, to)
                 }
+                AudioRenderThreadMsg::RemoveNode(id) => context.graph.remove_node(id),
+                AudioRenderThreadMsg::ReleaseNode(id) => context.graph.release_node(id),
                 AudioRenderThreadMsg::SetSinkEosCallback(callback) => {
                     context.sink.set_eos_callback(callback);
                 }
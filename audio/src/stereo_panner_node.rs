@@ -0,0 +1,95 @@
+use block::{Block, Chunk, Tick, FRAMES_PER_BLOCK};
+use node::{AudioNodeEngine, BlockInfo};
+use node::{AudioNodeType, ChannelInfo, ChannelInterpretation};
+use panner_node::equal_power_pan;
+use param::{Param, ParamDescriptor, ParamType};
+
+#[derive(Copy, Clone, Debug)]
+pub struct StereoPannerNodeOptions {
+    pub pan: f32,
+}
+
+impl Default for StereoPannerNodeOptions {
+    fn default() -> Self {
+        StereoPannerNodeOptions { pan: 0. }
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#StereoPannerNode
+#[derive(AudioNodeCommon)]
+pub(crate) struct StereoPannerNode {
+    channel_info: ChannelInfo,
+    pan: Param,
+}
+
+impl StereoPannerNode {
+    pub fn new(options: StereoPannerNodeOptions, channel_info: ChannelInfo) -> Self {
+        Self {
+            channel_info,
+            pan: Param::new(options.pan, ParamDescriptor::new(0., -1., 1.)),
+        }
+    }
+
+    pub fn update_parameters(&mut self, info: &BlockInfo, tick: Tick) -> bool {
+        self.pan.update(info, tick)
+    }
+}
+
+impl AudioNodeEngine for StereoPannerNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::StereoPannerNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, info: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+
+        let block = &mut inputs.blocks[0];
+
+        if block.is_silence() {
+            // The param must still see every tick, and
+            // the output is always stereo
+            for frame in 0..FRAMES_PER_BLOCK.0 {
+                self.update_parameters(info, Tick(frame));
+            }
+            *block = Block::default();
+            block.repeat(2);
+            return inputs;
+        }
+
+        // The channel count is clamped to two, but be defensive
+        if block.chan_count() > 2 {
+            block.mix(2, ChannelInterpretation::Speakers);
+        }
+
+        block.explicit_repeat();
+
+        let mono = if block.chan_count() == 1 {
+            block.resize_silence(2);
+            true
+        } else {
+            debug_assert!(block.chan_count() == 2);
+            false
+        };
+
+        let (l, r) = block.data_mut().split_at_mut(FRAMES_PER_BLOCK.0 as usize);
+        let mut pan = self.pan.value();
+        for frame in 0..FRAMES_PER_BLOCK.0 {
+            if self.update_parameters(info, Tick(frame)) {
+                pan = self.pan.value();
+            }
+            let index = frame as usize;
+            let (out_l, out_r) = equal_power_pan(pan, mono, l[index], r[index]);
+            l[index] = out_l;
+            r[index] = out_r;
+        }
+
+        inputs
+    }
+
+    fn get_param(&mut self, id: ParamType) -> &mut Param {
+        match id {
+            ParamType::Pan => &mut self.pan,
+            _ => panic!("Unknown param {:?} for StereoPannerNode", id),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use block::{Chunk, FRAMES_PER_BLOCK_USIZE};
+use node::{AudioNodeEngine, AudioNodeError, BlockInfo};
+use node::{AudioNodeType, ChannelInfo};
+use std::f64::consts::PI;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OverSampleType {
+    None,
+    Double,
+    Quadruple,
+}
+
+#[derive(Clone, Debug)]
+pub struct WaveShaperNodeOptions {
+    pub curve: Option<Vec<f32>>,
+    pub oversample: OverSampleType,
+}
+
+impl WaveShaperNodeOptions {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        validate_curve(&self.curve)
+    }
+}
+
+impl Default for WaveShaperNodeOptions {
+    fn default() -> Self {
+        WaveShaperNodeOptions {
+            curve: None,
+            oversample: OverSampleType::None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum WaveShaperNodeMessage {
+    SetCurve(Option<Vec<f32>>),
+    SetOverSample(OverSampleType),
+}
+
+impl WaveShaperNodeMessage {
+    pub(crate) fn validate(&self) -> Result<(), AudioNodeError> {
+        match *self {
+            WaveShaperNodeMessage::SetCurve(ref curve) => validate_curve(curve),
+            WaveShaperNodeMessage::SetOverSample(_) => Ok(()),
+        }
+    }
+}
+
+/// A curve needs at least two points
+///
+/// https://webaudio.github.io/web-audio-api/#dom-waveshapernode-curve
+fn validate_curve(curve: &Option<Vec<f32>>) -> Result<(), AudioNodeError> {
+    match *curve {
+        Some(ref curve) if curve.len() < 2 => Err(AudioNodeError::InvalidState),
+        _ => Ok(()),
+    }
+}
+
+/// Number of taps of the anti-aliasing filter
+const TAPS: usize = 32;
+
+/// A windowed sinc lowpass filter cutting off at half the nyquist
+/// frequency, which is the original nyquist frequency after upsampling
+/// by a factor of two
+fn lowpass_kernel() -> Vec<f32> {
+    let center = (TAPS - 1) as f64 / 2.;
+    let kernel: Vec<f64> = (0..TAPS)
+        .map(|n| {
+            let t = (n as f64 - center) / 2.;
+            let sinc = (PI * t).sin() / (PI * t);
+            // Blackman window
+            let phase = 2. * PI * n as f64 / (TAPS - 1) as f64;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2. * phase).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    kernel.iter().map(|h| (h / sum) as f32).collect()
+}
+
+/// The input history needed to resample one channel by a factor of two
+struct Resampler {
+    /// The last input samples, followed by the current input
+    buffer: Vec<f32>,
+    /// How many samples of history are kept
+    history: usize,
+}
+
+impl Resampler {
+    fn new(history: usize) -> Self {
+        Resampler {
+            buffer: vec![0.; history],
+            history,
+        }
+    }
+
+    /// Append the input to the history, returning the whole buffer
+    fn push(&mut self, input: &[f32]) -> &[f32] {
+        let history = self.history;
+        let len = self.buffer.len();
+        self.buffer.drain(..len - history);
+        self.buffer.extend_from_slice(input);
+        &self.buffer
+    }
+
+    /// Zero-stuff the input to twice its rate, and filter out the images
+    fn upsample(&mut self, kernel: &[f32], input: &[f32], output: &mut [f32]) {
+        debug_assert!(output.len() == 2 * input.len());
+        let history = self.history;
+        let buffer = self.push(input);
+        for (n, pair) in output.chunks_mut(2).enumerate() {
+            for (phase, out) in pair.iter_mut().enumerate() {
+                // Only every other tap lands on a nonzero sample, the gain
+                // of two makes up for the inserted zeroes
+                let sum: f32 = kernel[phase..]
+                    .iter()
+                    .step_by(2)
+                    .enumerate()
+                    .map(|(k, h)| h * buffer[history + n - k])
+                    .sum();
+                *out = 2. * sum;
+            }
+        }
+    }
+
+    /// Filter the input, and keep every other sample
+    fn downsample(&mut self, kernel: &[f32], input: &[f32], output: &mut [f32]) {
+        debug_assert!(input.len() == 2 * output.len());
+        let history = self.history;
+        let buffer = self.push(input);
+        for (n, out) in output.iter_mut().enumerate() {
+            let newest = history + 2 * n + 1;
+            *out = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, h)| h * buffer[newest - k])
+                .sum();
+        }
+    }
+}
+
+/// The resampling state for a single channel
+struct ChannelState {
+    /// From the original rate to twice it, and from there to four times it
+    up: [Resampler; 2],
+    /// From four times the rate to twice it, and from there back to the
+    /// original rate
+    down: [Resampler; 2],
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        ChannelState {
+            up: [Resampler::new(TAPS / 2 - 1), Resampler::new(TAPS / 2 - 1)],
+            down: [Resampler::new(TAPS - 1), Resampler::new(TAPS - 1)],
+        }
+    }
+}
+
+/// Map samples through the curve, interpolating linearly between its points
+///
+/// https://webaudio.github.io/web-audio-api/#dom-waveshapernode-curve
+fn shape(curve: &[f32], data: &mut [f32]) {
+    let last = curve.len() - 1;
+    for sample in data.iter_mut() {
+        let v = last as f32 * (*sample + 1.) / 2.;
+        *sample = if !(v > 0.) {
+            // This includes NaN
+            curve[0]
+        } else if v >= last as f32 {
+            curve[last]
+        } else {
+            let k = v.floor();
+            let f = v - k;
+            let k = k as usize;
+            (1. - f) * curve[k] + f * curve[k + 1]
+        };
+    }
+}
+
+/// https://webaudio.github.io/web-audio-api/#WaveShaperNode
+///
+/// When oversampling, each channel is upsampled by steps of two with a
+/// lowpass filter, shaped, and filtered again before being downsampled,
+/// to avoid the aliasing caused by the harmonics the curve introduces.
+#[derive(AudioNodeCommon)]
+pub(crate) struct WaveShaperNode {
+    channel_info: ChannelInfo,
+    curve: Option<Vec<f32>>,
+    oversample: OverSampleType,
+    kernel: Vec<f32>,
+    channels: Vec<ChannelState>,
+    /// Scratch space for the signal at twice the rate
+    double: Vec<f32>,
+    /// Scratch space for the signal at four times the rate
+    quadruple: Vec<f32>,
+    /// Whether the oversampling filters still hold some of the last input
+    tail: bool,
+}
+
+impl WaveShaperNode {
+    pub fn new(options: WaveShaperNodeOptions, channel_info: ChannelInfo) -> Self {
+        let mut node = Self {
+            channel_info,
+            curve: None,
+            oversample: options.oversample,
+            kernel: lowpass_kernel(),
+            channels: Vec::new(),
+            double: vec![0.; 2 * FRAMES_PER_BLOCK_USIZE],
+            quadruple: vec![0.; 4 * FRAMES_PER_BLOCK_USIZE],
+            tail: false,
+        };
+        node.set_curve(options.curve);
+        node
+    }
+
+    fn set_curve(&mut self, curve: Option<Vec<f32>>) {
+        // Shorter curves are rejected on the control thread, there is no
+        // way to shape anything with them
+        self.curve = curve.filter(|curve| curve.len() > 1);
+    }
+
+    pub fn handle_message(&mut self, message: WaveShaperNodeMessage, _: f32) {
+        match message {
+            WaveShaperNodeMessage::SetCurve(curve) => self.set_curve(curve),
+            WaveShaperNodeMessage::SetOverSample(oversample) => {
+                if oversample != self.oversample {
+                    // The filter history belongs to the previous rate
+                    self.channels.clear();
+                }
+                self.oversample = oversample;
+            }
+        }
+    }
+}
+
+impl AudioNodeEngine for WaveShaperNode {
+    fn node_type(&self) -> AudioNodeType {
+        AudioNodeType::WaveShaperNode
+    }
+
+    fn process(&mut self, mut inputs: Chunk, _: &BlockInfo) -> Chunk {
+        debug_assert!(inputs.len() == 1);
+
+        let curve = match self.curve {
+            Some(ref curve) => curve,
+            // Without a curve, the input is passed through
+            None => return inputs,
+        };
+
+        // The filters are shorter than a block, so their tail only lasts
+        // for the block after the last non-silent input
+        self.tail = self.oversample != OverSampleType::None && !inputs.blocks[0].is_silence();
+
+        // Silence may not shape to silence, and each channel
+        // has its own filter state
+        let block = &mut inputs.blocks[0];
+        block.explicit_repeat();
+        let chans = block.chan_count() as usize;
+        while self.channels.len() < chans {
+            self.channels.push(ChannelState::new());
+        }
+
+        let kernel = &self.kernel;
+        for (chan, state) in self.channels.iter_mut().enumerate().take(chans) {
+            let data = block.data_chan_mut(chan as u8);
+            match self.oversample {
+                OverSampleType::None => shape(curve, data),
+                OverSampleType::Double => {
+                    let double = &mut self.double;
+                    state.up[0].upsample(kernel, data, double);
+                    shape(curve, double);
+                    state.down[1].downsample(kernel, double, data);
+                }
+                OverSampleType::Quadruple => {
+                    let double = &mut self.double;
+                    let quadruple = &mut self.quadruple;
+                    state.up[0].upsample(kernel, data, double);
+                    state.up[1].upsample(kernel, double, quadruple);
+                    shape(curve, quadruple);
+                    state.down[0].downsample(kernel, quadruple, double);
+                    state.down[1].downsample(kernel, double, data);
+                }
+            }
+        }
+
+        inputs
+    }
+
+    fn has_remaining_output(&self, _: &BlockInfo) -> bool {
+        self.tail
+    }
+
+    make_message_handler!(WaveShaperNode: handle_message);
+}
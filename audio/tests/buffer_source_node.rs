@@ -0,0 +1,129 @@
+extern crate servo_media_audio;
+
+use servo_media_audio::buffer_source_node::{
+    AudioBufferSourceNodeMessage, AudioBufferSourceNodeOptions,
+};
+use servo_media_audio::context::{AudioContext, AudioContextOptions, OfflineAudioContextOptions};
+use servo_media_audio::decoder::DummyAudioDecoder;
+use servo_media_audio::node::OnEndedCallback;
+use servo_media_audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media_audio::sink::DummyAudioSink;
+use servo_media_audio::AudioBackend;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+struct TestBackend;
+
+impl AudioBackend for TestBackend {
+    type Decoder = DummyAudioDecoder;
+    type Sink = DummyAudioSink;
+    fn make_decoder() -> Self::Decoder {
+        DummyAudioDecoder
+    }
+
+    fn make_sink() -> Result<Self::Sink, ()> {
+        Ok(DummyAudioSink)
+    }
+}
+
+/// Play a short buffer in an offline context, and tell whether onended
+/// fired by the time rendering is over
+fn onended_fires(release: bool) -> bool {
+    let mut options = OfflineAudioContextOptions::default();
+    options.length = 4096;
+    let context: AudioContext<TestBackend> =
+        AudioContext::new(AudioContextOptions::OfflineAudioContext(options));
+
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    context.set_eos_callback(Box::new(move |_| {
+        sender.lock().unwrap().send(()).unwrap();
+    }));
+
+    let source = context.create_node(
+        AudioNodeInit::AudioBufferSourceNode(AudioBufferSourceNodeOptions::default()),
+        Default::default(),
+    );
+    context.message_node(
+        source,
+        AudioNodeMessage::AudioBufferSourceNode(AudioBufferSourceNodeMessage::SetBuffer(Some(
+            vec![1.; 1000].into(),
+        ))),
+    );
+    let (ended_sender, ended_receiver) = mpsc::channel();
+    context.message_node(
+        source,
+        AudioNodeMessage::AudioScheduledSourceNode(
+            AudioScheduledSourceNodeMessage::RegisterOnEndedCallback(OnEndedCallback::new(
+                move || {
+                    ended_sender.send(()).unwrap();
+                },
+            )),
+        ),
+    );
+    context.message_node(
+        source,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.connect_ports(source.output(0), context.dest_node().input(0));
+    if release {
+        context.release_node(source);
+    }
+
+    let _ = context.resume();
+    receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    let ended = ended_receiver.try_recv().is_ok();
+    let _ = context.close();
+    ended
+}
+
+#[test]
+fn onended_fires_at_end_of_buffer() {
+    assert!(onended_fires(false));
+}
+
+#[test]
+fn onended_fires_for_released_source() {
+    assert!(onended_fires(true));
+}
+
+#[test]
+fn start_at_block_boundary() {
+    let mut options = OfflineAudioContextOptions::default();
+    options.length = 1024;
+    let sample_rate = options.sample_rate;
+    let context: AudioContext<TestBackend> =
+        AudioContext::new(AudioContextOptions::OfflineAudioContext(options));
+
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    context.set_eos_callback(Box::new(move |buffer| {
+        let buffer: &[f32] = (*buffer).as_ref();
+        sender.lock().unwrap().send(buffer.to_vec()).unwrap();
+    }));
+
+    let source = context.create_node(
+        AudioNodeInit::AudioBufferSourceNode(AudioBufferSourceNodeOptions::default()),
+        Default::default(),
+    );
+    context.message_node(
+        source,
+        AudioNodeMessage::AudioBufferSourceNode(AudioBufferSourceNodeMessage::SetBuffer(Some(
+            vec![1.; 1000].into(),
+        ))),
+    );
+    context.message_node(
+        source,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(
+            256. / sample_rate as f64,
+        )),
+    );
+    context.connect_ports(source.output(0), context.dest_node().input(0));
+
+    let _ = context.resume();
+    let output = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    let _ = context.close();
+    assert!(output[..256].iter().all(|&x| x == 0.));
+    assert!(output[256..1024].iter().all(|&x| x == 1.));
+}
@@ -16,6 +16,7 @@ use servo_media_player::PlayerBackend;
 
 pub mod audio_decoder;
 pub mod audio_sink;
+pub mod media_stream;
 pub mod player;
 
 #[derive(Debug)]
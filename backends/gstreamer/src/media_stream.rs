@@ -0,0 +1,97 @@
+use super::BackendError;
+use byte_slice_cast::*;
+use gst;
+use gst::prelude::*;
+use gst_app::AppSrc;
+use gst_audio;
+use servo_media_audio::block::{Block, FRAMES_PER_BLOCK};
+use servo_media_audio::media_stream_destination_node::MediaStreamConsumer;
+use servo_media_audio::node::BlockInfo;
+
+/// Pushes the audio of a MediaStreamDestinationNode to an appsrc, which
+/// can then be linked into any pipeline
+pub struct GStreamerAudioStream {
+    appsrc: AppSrc,
+    audio_info: Option<gst_audio::AudioInfo>,
+}
+
+impl GStreamerAudioStream {
+    pub fn new() -> Result<Self, BackendError> {
+        gst::init().map_err(BackendError::Gstreamer)?;
+
+        let appsrc = gst::ElementFactory::make("appsrc", None)
+            .ok_or(BackendError::ElementCreationFailed("appsrc"))?;
+        appsrc
+            .set_property("is-live", &true.to_value())
+            .map_err(|e| BackendError::SetPropertyFailed(e.0))?;
+        let appsrc = appsrc.downcast::<AppSrc>().unwrap();
+        appsrc.set_property_format(gst::Format::Time);
+        Ok(Self {
+            appsrc,
+            audio_info: None,
+        })
+    }
+
+    /// The appsrc the audio is pushed to
+    pub fn appsrc(&self) -> AppSrc {
+        self.appsrc.clone()
+    }
+
+    fn set_audio_info_if_changed(
+        &mut self,
+        sample_rate: u32,
+        channels: u32,
+    ) -> Result<(), BackendError> {
+        if let Some(ref info) = self.audio_info {
+            if info.rate() == sample_rate && info.channels() == channels {
+                return Ok(());
+            }
+        }
+        let audio_info =
+            gst_audio::AudioInfo::new(gst_audio::AUDIO_FORMAT_F32, sample_rate, channels)
+                .build()
+                .ok_or(BackendError::AudioInfoFailed)?;
+        self.appsrc.set_caps(&audio_info.to_caps().unwrap());
+        self.audio_info = Some(audio_info);
+        Ok(())
+    }
+
+    fn push_block(&mut self, mut block: Block, info: &BlockInfo) -> Result<(), BackendError> {
+        let sample_rate = info.sample_rate as u64;
+        self.set_audio_info_if_changed(sample_rate as u32, block.chan_count().into())?;
+
+        let mut data = block.interleave();
+        let data = data.as_mut_byte_slice().expect("casting failed");
+        let mut buffer = gst::Buffer::with_size(data.len()).ok_or(BackendError::BufferReadError)?;
+        {
+            let buffer = buffer.get_mut().unwrap();
+            // Timestamp the buffers with the context time, computing the
+            // duration from the next timestamp to prevent rounding errors
+            let frame = info.frame.0;
+            let pts = frame
+                .mul_div_floor(gst::SECOND_VAL, sample_rate)
+                .unwrap()
+                .into();
+            let next_pts: gst::ClockTime = (frame + FRAMES_PER_BLOCK.0)
+                .mul_div_floor(gst::SECOND_VAL, sample_rate)
+                .unwrap()
+                .into();
+            buffer.set_pts(pts);
+            buffer.set_duration(next_pts - pts);
+            buffer.copy_from_slice(0, data).expect("copying failed");
+        }
+
+        self.appsrc
+            .push_buffer(buffer)
+            .into_result()
+            .map(|_| ())
+            .map_err(BackendError::Flow)
+    }
+}
+
+impl MediaStreamConsumer for GStreamerAudioStream {
+    fn push(&mut self, block: Block, info: &BlockInfo) {
+        // Nothing may be consuming the stream yet, or anymore
+        let _ = self.push_block(block, info);
+    }
+}
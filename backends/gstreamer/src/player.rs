@@ -1,11 +1,15 @@
 use super::BackendError;
+use byte_slice_cast::*;
 use glib;
 use glib::*;
 use gst;
+use gst::prelude::*;
 use gst_app::{self, AppSrcCallbacks, AppStreamType};
+use gst_audio;
 use gst_player;
 use gst_player::{PlayerMediaInfo, PlayerStreamInfoExt};
 use ipc_channel::ipc::IpcSender;
+use servo_media_player::audio::AudioRenderer;
 use servo_media_player::frame::{Frame, FrameRenderer};
 use servo_media_player::metadata::Metadata;
 use servo_media_player::{PlaybackState, Player, PlayerEvent, StreamType};
@@ -29,6 +33,88 @@ fn frame_from_sample(sample: &gst::Sample) -> Result<Frame, ()> {
     Ok(Frame::new(width, height, Arc::new(data)))
 }
 
+/// Split an interleaved F32 audio sample into one buffer per channel,
+/// returning them along with the sample rate
+fn audio_from_sample(sample: &gst::Sample) -> Result<(Vec<Vec<f32>>, u32), ()> {
+    let caps = sample.get_caps().ok_or_else(|| ())?;
+    let info = gst_audio::AudioInfo::from_caps(&caps).ok_or_else(|| ())?;
+    let channels = info.channels() as usize;
+    if channels == 0 {
+        return Err(());
+    }
+
+    let buffer = sample.get_buffer().ok_or_else(|| ())?;
+    let map = buffer.map_readable().ok_or_else(|| ())?;
+    let data = map.as_slice().as_slice_of::<f32>().map_err(|_| ())?;
+
+    let frames = data.len() / channels;
+    let mut planar = vec![Vec::with_capacity(frames); channels];
+    for frame in data.chunks(channels) {
+        for (channel, sample) in planar.iter_mut().zip(frame) {
+            channel.push(*sample);
+        }
+    }
+    Ok((planar, info.rate()))
+}
+
+/// Create the audio sink of the player, which looks like
+///
+/// tee ! queue ! volume ! autoaudiosink
+/// tee ! queue ! audioconvert ! appsink
+///
+/// The first branch plays the audio, until it gets muted by registering
+/// an audio renderer, and the second one feeds audio renderers. Both are
+/// synchronized on the pipeline clock, so that rendered audio stays in
+/// sync with the video.
+fn audio_sink() -> Result<(gst::Element, gst::Element, gst_app::AppSink), BackendError> {
+    let make = |factory: &'static str| {
+        gst::ElementFactory::make(factory, None).ok_or(BackendError::ElementCreationFailed(factory))
+    };
+    let tee = make("tee")?;
+    let output_queue = make("queue")?;
+    let volume = make("volume")?;
+    let output = make("autoaudiosink")?;
+    let render_queue = make("queue")?;
+    let convert = make("audioconvert")?;
+    let appsink = make("appsink")?;
+
+    let bin = gst::Bin::new(None);
+    bin.add_many(&[
+        &tee,
+        &output_queue,
+        &volume,
+        &output,
+        &render_queue,
+        &convert,
+        &appsink,
+    ]).map_err(|e| BackendError::PipelineFailed(e.0))?;
+    gst::Element::link_many(&[&tee, &output_queue, &volume, &output])
+        .map_err(|e| BackendError::PipelineFailed(e.0))?;
+    gst::Element::link_many(&[&tee, &render_queue, &convert, &appsink])
+        .map_err(|e| BackendError::PipelineFailed(e.0))?;
+
+    let pad = tee
+        .get_static_pad("sink")
+        .ok_or(BackendError::GetStaticPadFailed("sink"))?;
+    let ghost_pad =
+        gst::GhostPad::new(Some("sink"), &pad).ok_or(BackendError::PipelineFailed("ghost pad"))?;
+    bin.add_pad(&ghost_pad)
+        .map_err(|e| BackendError::PipelineFailed(e.0))?;
+
+    let appsink = appsink.dynamic_cast::<gst_app::AppSink>().unwrap();
+    let format = if cfg!(target_endian = "little") {
+        "F32LE"
+    } else {
+        "F32BE"
+    };
+    appsink.set_caps(&gst::Caps::new_simple(
+        "audio/x-raw",
+        &[("format", &format), ("layout", &"interleaved")],
+    ));
+
+    Ok((bin.upcast(), volume, appsink))
+}
+
 fn metadata_from_media_info(media_info: &PlayerMediaInfo) -> Result<Metadata, ()> {
     let dur = media_info.get_duration();
     let duration = if dur != gst::ClockTime::none() {
@@ -85,10 +171,15 @@ struct PlayerInner {
     player: gst_player::Player,
     appsrc: Option<gst_app::AppSrc>,
     appsink: gst_app::AppSink,
+    audio_appsink: gst_app::AppSink,
+    /// The volume element of the audio output, muted once audio is
+    /// routed to renderers
+    audio_output: gst::Element,
     input_size: u64,
     stream_type: Option<AppStreamType>,
     subscribers: Vec<IpcSender<PlayerEvent>>,
     renderers: Vec<Arc<Mutex<FrameRenderer>>>,
+    audio_renderers: Vec<Arc<Mutex<AudioRenderer>>>,
     last_metadata: Option<Metadata>,
 }
 
@@ -109,6 +200,17 @@ impl PlayerInner {
         Ok(())
     }
 
+    pub fn register_audio_renderer(
+        &mut self,
+        renderer: Arc<Mutex<AudioRenderer>>,
+    ) -> Result<(), BackendError> {
+        self.audio_output
+            .set_property("mute", &true.to_value())
+            .map_err(|e| BackendError::SetPropertyFailed(e.0))?;
+        self.audio_renderers.push(renderer);
+        Ok(())
+    }
+
     pub fn notify(&self, event: PlayerEvent) {
         for sender in &self.subscribers {
             sender.send(event.clone()).unwrap();
@@ -125,6 +227,21 @@ impl PlayerInner {
         Ok(())
     }
 
+    pub fn render_audio(&self, sample: &gst::Sample) -> Result<(), ()> {
+        if self.audio_renderers.is_empty() {
+            return Ok(());
+        }
+        let (channels, sample_rate) = audio_from_sample(&sample)?;
+
+        for renderer in &self.audio_renderers {
+            renderer
+                .lock()
+                .unwrap()
+                .render(channels.clone(), sample_rate);
+        }
+        Ok(())
+    }
+
     pub fn set_input_size(&mut self, size: u64) -> Result<(), BackendError> {
         // Set input_size to proxy its value, since it
         // could be set by the user before calling .setup().
@@ -260,6 +377,11 @@ impl GStreamerPlayer {
             ],
         ));
 
+        let (audio_sink, audio_output, audio_appsink) = audio_sink()?;
+        pipeline
+            .set_property("audio-sink", &audio_sink.to_value())
+            .map_err(|e| BackendError::SetPropertyFailed(e.0))?;
+
         // There's a known bug in gstreamer that may cause a wrong transition
         // to the ready state while setting the uri property:
         // http://cgit.freedesktop.org/gstreamer/gst-plugins-bad/commit/?id=afbbc3a97ec391c6a582f3c746965fdc3eb3e1f3
@@ -275,10 +397,13 @@ impl GStreamerPlayer {
             player,
             appsrc: None,
             appsink: video_sink,
+            audio_appsink,
+            audio_output,
             input_size: 0,
             stream_type: None,
             subscribers: Vec::new(),
             renderers: Vec::new(),
+            audio_renderers: Vec::new(),
             last_metadata: None,
         })));
 
@@ -410,6 +535,24 @@ impl GStreamerPlayer {
                 .build(),
         );
 
+        let inner_clone = inner.clone();
+        inner.lock().unwrap().audio_appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::new()
+                .new_preroll(|_| gst::FlowReturn::Ok)
+                .new_sample(move |appsink| {
+                    let sample = match appsink.pull_sample() {
+                        None => return gst::FlowReturn::Eos,
+                        Some(sample) => sample,
+                    };
+
+                    match inner_clone.lock().unwrap().render_audio(&sample) {
+                        Ok(_) => return gst::FlowReturn::Ok,
+                        Err(_) => return gst::FlowReturn::Error,
+                    };
+                })
+                .build(),
+        );
+
         let (receiver, error_handler_id) = {
             let inner_clone = inner.clone();
             let mut inner = inner.lock().unwrap();
@@ -534,6 +677,7 @@ impl Player for GStreamerPlayer {
 
     inner_player_proxy!(register_event_handler, sender, IpcSender<PlayerEvent>);
     inner_player_proxy!(register_frame_renderer, renderer, Arc<Mutex<FrameRenderer>>);
+    inner_player_proxy!(register_audio_renderer, renderer, Arc<Mutex<AudioRenderer>>);
     inner_player_proxy!(play);
     inner_player_proxy!(pause);
     inner_player_proxy!(stop);
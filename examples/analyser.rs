@@ -0,0 +1,70 @@
+extern crate servo_media;
+
+use servo_media::audio::analyser_node::{AnalyserNodeMessage, AnalyserNodeOptions};
+use servo_media::audio::context::{AudioContextOptions, RealTimeAudioContextOptions};
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::ServoMedia;
+use std::sync::{mpsc, Arc};
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let options = RealTimeAudioContextOptions::default();
+    let sample_rate = options.sample_rate;
+    let context =
+        servo_media.create_audio_context(AudioContextOptions::RealTimeAudioContext(options));
+    let osc = context.create_node(
+        AudioNodeInit::OscillatorNode(Default::default()),
+        Default::default(),
+    );
+    let options = AnalyserNodeOptions::default();
+    let analyser = context.create_node(
+        AudioNodeInit::AnalyserEngineNode(options),
+        Default::default(),
+    );
+    let dest = context.dest_node();
+    context.connect_ports(osc.output(0), analyser.input(0));
+    context.connect_ports(analyser.output(0), dest.input(0));
+    context.message_node(
+        osc,
+        AudioNodeMessage::SetParam(
+            ParamType::Frequency,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Exponential, 3520., 3.),
+        ),
+    );
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    let _ = context.resume();
+
+    // Follow the frequency of the oscillator by looking for the loudest bin
+    let mut data = vec![0.; options.fft_size / 2];
+    for _ in 0..12 {
+        thread::sleep(time::Duration::from_millis(250));
+        let (tx, rx) = mpsc::channel();
+        context.message_node(
+            analyser,
+            AudioNodeMessage::AnalyserNode(AnalyserNodeMessage::GetFloatFrequencyData(data, tx)),
+        );
+        data = rx.recv().unwrap();
+        let loudest = (0..data.len())
+            .max_by(|&a, &b| data[a].partial_cmp(&data[b]).unwrap())
+            .unwrap();
+        println!(
+            "{:.2}s: loudest around {:.0}Hz, at {:.1}dB",
+            context.current_time(),
+            loudest as f32 * sample_rate / options.fft_size as f32,
+            data[loudest]
+        );
+    }
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
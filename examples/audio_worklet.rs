@@ -0,0 +1,112 @@
+extern crate servo_media;
+
+use servo_media::audio::audio_worklet_node::{AudioWorkletNodeOptions, AudioWorkletProcessor};
+use servo_media::audio::block::Block;
+use servo_media::audio::node::{
+    AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage, BlockInfo,
+};
+use servo_media::audio::param::{
+    ParamDescriptor, ParamRate, ParamType, RampKind, UserAutomationEvent,
+};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+/// Reduces the bit depth and the sample rate of its input
+struct BitCrusher {
+    /// The last sample kept for each channel
+    held: Vec<f32>,
+    /// How far we are through the current held sample
+    phase: f32,
+}
+
+impl AudioWorkletProcessor for BitCrusher {
+    fn process(
+        &mut self,
+        inputs: &[Block],
+        outputs: &mut [Block],
+        params: &[Vec<f32>],
+        _: &BlockInfo,
+    ) -> bool {
+        let (bit_depth, frequency_reduction) = (&params[0], &params[1]);
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        self.held.resize(input.chan_count() as usize, 0.);
+        // The bit depth is k-rate, so it has a single value per block
+        let step = 0.5f32.powf(bit_depth[0]);
+        for frame in 0..input.data_chan(0).len() {
+            let reduction = if frequency_reduction.len() > 1 {
+                frequency_reduction[frame]
+            } else {
+                frequency_reduction[0]
+            };
+            self.phase += reduction;
+            let hold = self.phase < 1.;
+            if !hold {
+                self.phase -= 1.;
+            }
+            for chan in 0..input.chan_count() {
+                let held = &mut self.held[chan as usize];
+                if !hold {
+                    *held = step * (input.data_chan(chan)[frame] / step + 0.5).floor();
+                }
+                output.data_chan_mut(chan)[frame] = *held;
+            }
+        }
+        // This is an effect, there is nothing to output without input
+        false
+    }
+}
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let osc = context.create_node(
+        AudioNodeInit::OscillatorNode(Default::default()),
+        Default::default(),
+    );
+    let mut options = AudioWorkletNodeOptions::new(Box::new(BitCrusher {
+        held: Vec::new(),
+        phase: 0.,
+    }));
+    options.parameters = vec![
+        ParamDescriptor {
+            automation_rate: ParamRate::KRate,
+            ..ParamDescriptor::new(12., 1., 16.)
+        },
+        ParamDescriptor::new(0.5, 0., 1.),
+    ];
+    let crusher = context.create_node(AudioNodeInit::AudioWorkletNode(options), Default::default());
+    let dest = context.dest_node();
+    context.connect_ports(osc.output(0), crusher.input(0));
+    context.connect_ports(crusher.output(0), dest.input(0));
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    // Lower the bit depth after a second, and sweep the frequency reduction
+    context.message_node(
+        crusher,
+        AudioNodeMessage::SetParam(
+            ParamType::Custom(0),
+            UserAutomationEvent::SetValueAtTime(3., 1.),
+        ),
+    );
+    context.message_node(
+        crusher,
+        AudioNodeMessage::SetParam(
+            ParamType::Custom(1),
+            UserAutomationEvent::RampToValueAtTime(RampKind::Exponential, 0.01, 3.),
+        ),
+    );
+    let _ = context.resume();
+    thread::sleep(time::Duration::from_millis(4000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -7,6 +7,7 @@ use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSo
 use servo_media::audio::oscillator_node::OscillatorNodeOptions;
 use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
 use servo_media::ServoMedia;
+use std::sync::mpsc;
 use std::sync::Arc;
 use std::{thread, time};
 
@@ -15,7 +16,10 @@ fn run_example(servo_media: Arc<ServoMedia>) {
     let dest = context.dest_node();
     let mut options = OscillatorNodeOptions::default();
     options.freq = 100.;
-    let osc1 = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    let osc1 = context.create_node(
+        AudioNodeInit::OscillatorNode(options.clone()),
+        Default::default(),
+    );
     options.freq = 800.;
     let osc2 = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
     let mut options = BiquadFilterNodeOptions::default();
@@ -25,6 +29,21 @@ fn run_example(servo_media: Arc<ServoMedia>) {
     context.connect_ports(osc1.output(0), biquad.input(0));
     context.connect_ports(osc2.output(0), biquad.input(0));
     context.connect_ports(biquad.output(0), dest.input(0));
+
+    let frequencies = vec![10., 50., 100., 800., 10000.];
+    let (tx, rx) = mpsc::channel();
+    context.message_node(
+        biquad,
+        AudioNodeMessage::BiquadFilterNode(BiquadFilterNodeMessage::GetFrequencyResponse(
+            frequencies.clone(),
+            tx,
+        )),
+    );
+    let (magnitude, phase) = rx.recv().unwrap();
+    for ((freq, mag), phase) in frequencies.iter().zip(magnitude).zip(phase) {
+        println!("{}Hz: magnitude {}, phase {}", freq, mag, phase);
+    }
+
     let _ = context.resume();
     context.message_node(
         osc1,
@@ -0,0 +1,71 @@
+extern crate servo_media;
+
+use servo_media::audio::buffer_source_node::{
+    AudioBuffer, AudioBufferSourceNodeMessage, AudioBufferSourceNodeOptions,
+};
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::ServoMedia;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let sample_rate = 44100.;
+    // One second of a decaying 220Hz tone, followed by a one second 330Hz tone
+    let buffer: Vec<f32> = (0..2 * sample_rate as usize)
+        .map(|i| {
+            let t = i as f32 / sample_rate;
+            if t < 1. {
+                0.5 * (2. * PI * 220. * t).sin() * (-3. * t).exp()
+            } else {
+                0.25 * (2. * PI * 330. * t).sin()
+            }
+        })
+        .collect();
+    let mut options = AudioBufferSourceNodeOptions::default();
+    options.buffer = Some(AudioBuffer::from(buffer));
+    // Loop over the first quarter of a second of the decay
+    options.loop_enabled = true;
+    options.loop_start = Some(0.);
+    options.loop_end = Some(0.25);
+    let buffer_source = context.create_node(
+        AudioNodeInit::AudioBufferSourceNode(options),
+        Default::default(),
+    );
+    let dest = context.dest_node();
+    context.connect_ports(buffer_source.output(0), dest.input(0));
+    context.message_node(
+        buffer_source,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    let _ = context.resume();
+    thread::sleep(time::Duration::from_millis(2000));
+    // Move the loop to the second tone
+    context.message_node(
+        buffer_source,
+        AudioNodeMessage::AudioBufferSourceNode(AudioBufferSourceNodeMessage::SetLoopEnd(2.)),
+    );
+    context.message_node(
+        buffer_source,
+        AudioNodeMessage::AudioBufferSourceNode(AudioBufferSourceNodeMessage::SetLoopStart(1.5)),
+    );
+    thread::sleep(time::Duration::from_millis(3000));
+    // Let the buffer play out to its end
+    context.message_node(
+        buffer_source,
+        AudioNodeMessage::AudioBufferSourceNode(AudioBufferSourceNodeMessage::SetLoopEnabled(
+            false,
+        )),
+    );
+    thread::sleep(time::Duration::from_millis(1000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,78 @@
+extern crate servo_media;
+
+use servo_media::audio::constant_source_node::ConstantSourceNodeOptions;
+use servo_media::audio::gain_node::GainNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 220.;
+    let osc1 = context.create_node(
+        AudioNodeInit::OscillatorNode(options.clone()),
+        Default::default(),
+    );
+    options.freq = 330.;
+    let osc2 = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    // The gain params start at zero, the constant source provides their value
+    let options = GainNodeOptions { gain: 0. };
+    let gain1 = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+    let gain2 = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+    let options = ConstantSourceNodeOptions { offset: 0. };
+    let cs = context.create_node(
+        AudioNodeInit::ConstantSourceNode(options),
+        Default::default(),
+    );
+    context.connect_ports(osc1.output(0), gain1.input(0));
+    context.connect_ports(osc2.output(0), gain2.input(0));
+    context.connect_ports(gain1.output(0), dest.input(0));
+    context.connect_ports(gain2.output(0), dest.input(0));
+    // A single automation curve on the constant source controls both gains
+    context.connect_ports(cs.output(0), gain1.param(ParamType::Gain));
+    context.connect_ports(cs.output(0), gain2.param(ParamType::Gain));
+    let _ = context.resume();
+    context.message_node(
+        osc1,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.message_node(
+        osc2,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.message_node(
+        cs,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    // 0s - 1.5s: Fade in both oscillators
+    context.message_node(
+        cs,
+        AudioNodeMessage::SetParam(
+            ParamType::Offset,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Linear, 0.5, 1.5),
+        ),
+    );
+    // 1.5s - 3s: Fade them out again
+    context.message_node(
+        cs,
+        AudioNodeMessage::SetParam(
+            ParamType::Offset,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Linear, 0., 3.),
+        ),
+    );
+    thread::sleep(time::Duration::from_millis(3000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,52 @@
+extern crate rand;
+extern crate servo_media;
+
+use servo_media::audio::convolver_node::ConvolverNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 440.;
+    let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    // A stereo impulse response made of exponentially decaying noise,
+    // which sounds like a large room
+    let len = 2 * 44100;
+    let mut buffers = vec![Vec::with_capacity(len), Vec::with_capacity(len)];
+    for i in 0..len {
+        let decay = (-6. * i as f32 / len as f32).exp();
+        buffers[0].push((rand::random::<f32>() * 2. - 1.) * decay);
+        buffers[1].push((rand::random::<f32>() * 2. - 1.) * decay);
+    }
+    let options = ConvolverNodeOptions {
+        buffer: Some(buffers.into()),
+        normalize: true,
+    };
+    let convolver = context.create_node(AudioNodeInit::ConvolverNode(options), Default::default());
+    context.connect_ports(osc.output(0), convolver.input(0));
+    context.connect_ports(convolver.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Stop(0.2)),
+    );
+    thread::sleep(time::Duration::from_millis(3000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,50 @@
+extern crate servo_media;
+
+use servo_media::audio::delay_node::DelayNodeOptions;
+use servo_media::audio::gain_node::GainNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 440.;
+    let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    let options = DelayNodeOptions {
+        max_delay_time: 1.,
+        delay_time: 0.3,
+    };
+    let delay = context.create_node(AudioNodeInit::DelayNode(options), Default::default());
+    let options = GainNodeOptions { gain: 0.6 };
+    let feedback = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+    context.connect_ports(osc.output(0), dest.input(0));
+    context.connect_ports(osc.output(0), delay.input(0));
+    // The delayed signal is fed back into the delay, producing
+    // echoes that keep getting quieter
+    context.connect_ports(delay.output(0), feedback.input(0));
+    context.connect_ports(feedback.output(0), delay.input(0));
+    context.connect_ports(delay.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Stop(0.1)),
+    );
+    thread::sleep(time::Duration::from_millis(3000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
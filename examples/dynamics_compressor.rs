@@ -0,0 +1,57 @@
+extern crate servo_media;
+
+use servo_media::audio::dynamics_compressor_node::{
+    DynamicsCompressorNodeMessage, DynamicsCompressorNodeOptions,
+};
+use servo_media::audio::gain_node::GainNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::ServoMedia;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let compressor = context.create_node(
+        AudioNodeInit::DynamicsCompressionNode(DynamicsCompressorNodeOptions::default()),
+        Default::default(),
+    );
+    // Two loud oscillators would clip without the compressor
+    for &freq in &[440., 660.] {
+        let mut options = OscillatorNodeOptions::default();
+        options.freq = freq;
+        let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+        let options = GainNodeOptions { gain: 0.8 };
+        let gain = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+        context.connect_ports(osc.output(0), gain.input(0));
+        context.connect_ports(gain.output(0), compressor.input(0));
+        context.message_node(
+            osc,
+            AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+        );
+    }
+    context.connect_ports(compressor.output(0), dest.input(0));
+    let _ = context.resume();
+    for _ in 0..6 {
+        thread::sleep(time::Duration::from_millis(500));
+        let (tx, rx) = mpsc::channel();
+        context.message_node(
+            compressor,
+            AudioNodeMessage::DynamicsCompressionNode(DynamicsCompressorNodeMessage::GetReduction(
+                tx,
+            )),
+        );
+        println!("Reduction: {:?} dB", rx.recv().unwrap());
+    }
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,65 @@
+extern crate servo_media;
+
+use servo_media::audio::iir_filter_node::{IIRFilterNodeMessage, IIRFilterNodeOptions};
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::ServoMedia;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 100.;
+    let osc1 = context.create_node(
+        AudioNodeInit::OscillatorNode(options.clone()),
+        Default::default(),
+    );
+    options.freq = 3000.;
+    let osc2 = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    // A second order Butterworth lowpass at 500Hz, for a 44.1kHz sample rate
+    let options = IIRFilterNodeOptions {
+        feedforward: vec![0.0012075, 0.002415, 0.0012075],
+        feedback: vec![1., -1.899331, 0.904163],
+    };
+    let iir = context.create_node(AudioNodeInit::IIRFilterNode(options), Default::default());
+    context.connect_ports(osc1.output(0), iir.input(0));
+    context.connect_ports(osc2.output(0), iir.input(0));
+    context.connect_ports(iir.output(0), dest.input(0));
+
+    let frequencies = vec![0., 100., 500., 3000., 10000.];
+    let (tx, rx) = mpsc::channel();
+    context.message_node(
+        iir,
+        AudioNodeMessage::IIRFilterNode(IIRFilterNodeMessage::GetFrequencyResponse(
+            frequencies.clone(),
+            tx,
+        )),
+    );
+    let (magnitude, phase) = rx.recv().unwrap();
+    for ((freq, mag), phase) in frequencies.iter().zip(magnitude).zip(phase) {
+        println!("{}Hz: magnitude {}, phase {}", freq, mag, phase);
+    }
+
+    let _ = context.resume();
+    context.message_node(
+        osc1,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.message_node(
+        osc2,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    thread::sleep(time::Duration::from_millis(3000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
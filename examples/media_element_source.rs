@@ -0,0 +1,122 @@
+extern crate ipc_channel;
+extern crate servo_media;
+
+use ipc_channel::ipc;
+use servo_media::audio::biquad_filter_node::{BiquadFilterNodeOptions, FilterType};
+use servo_media::audio::media_element_source_node::MediaElementSourceNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage};
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::player::{PlayerEvent, StreamType};
+use servo_media::ServoMedia;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let args: Vec<_> = env::args().collect();
+    let default = "./examples/resources/viper_cut.ogg";
+    let filename: &str = if args.len() == 2 {
+        args[1].as_ref()
+    } else if Path::new(default).exists() {
+        default
+    } else {
+        panic!("Usage: cargo run --bin media_element_source <file_path>")
+    };
+    let mut file = File::open(filename).unwrap();
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).unwrap();
+
+    // Play the soundtrack through a lowpass filter opening up over ten
+    // seconds, instead of the player's audio output
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let (options, renderer) = MediaElementSourceNodeOptions::new();
+    let source = context.create_node(
+        AudioNodeInit::MediaElementSourceNode(options),
+        Default::default(),
+    );
+    let mut options = BiquadFilterNodeOptions::default();
+    options.filter = FilterType::LowPass;
+    options.frequency = 100.;
+    let biquad = context.create_node(AudioNodeInit::BiquadFilterNode(options), Default::default());
+    context.connect_ports(source.output(0), biquad.input(0));
+    context.connect_ports(biquad.output(0), dest.input(0));
+    context.message_node(
+        biquad,
+        AudioNodeMessage::SetParam(
+            ParamType::Frequency,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Exponential, 10000., 10.),
+        ),
+    );
+    let _ = context.resume();
+
+    let player = Arc::new(Mutex::new(servo_media.create_player()));
+    let (sender, receiver) = ipc::channel().unwrap();
+    player
+        .lock()
+        .unwrap()
+        .register_event_handler(sender)
+        .unwrap();
+    player
+        .lock()
+        .unwrap()
+        .register_audio_renderer(renderer)
+        .unwrap();
+    player
+        .lock()
+        .unwrap()
+        .set_input_size(bytes.len() as u64)
+        .unwrap();
+    player
+        .lock()
+        .unwrap()
+        .set_stream_type(StreamType::Stream)
+        .unwrap();
+
+    let player_clone = Arc::clone(&player);
+    let t = thread::spawn(move || {
+        for chunk in bytes.chunks(1024) {
+            if player_clone
+                .lock()
+                .unwrap()
+                .push_data(Vec::from(chunk))
+                .is_err()
+            {
+                break;
+            }
+        }
+        let _ = player_clone.lock().unwrap().end_of_stream();
+    });
+
+    player.lock().unwrap().play().unwrap();
+
+    while let Ok(event) = receiver.recv() {
+        match event {
+            PlayerEvent::EndOfStream => {
+                println!("\nEOF");
+                break;
+            }
+            PlayerEvent::Error => {
+                println!("\nError");
+                break;
+            }
+            PlayerEvent::PositionChanged(p) => eprint!("\rPosition: {}s", p),
+            _ => {}
+        }
+    }
+
+    let _ = t.join();
+    player.lock().unwrap().stop().unwrap();
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
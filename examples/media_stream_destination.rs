@@ -0,0 +1,77 @@
+extern crate servo_media;
+
+use servo_media::audio::media_stream_destination_node::{
+    MediaStreamDestinationNodeOptions, SampleLayout,
+};
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let osc = context.create_node(
+        AudioNodeInit::OscillatorNode(Default::default()),
+        Default::default(),
+    );
+    // Play the oscillator, while also recording it, with up to a second
+    // of audio waiting to be read
+    let (options, receiver) = MediaStreamDestinationNodeOptions::channel(SampleLayout::Planar, 350);
+    let stream = context.create_node(
+        AudioNodeInit::MediaStreamDestinationNode(options),
+        Default::default(),
+    );
+    let dest = context.dest_node();
+    context.connect_ports(osc.output(0), dest.input(0));
+    context.connect_ports(osc.output(0), stream.input(0));
+    context.message_node(
+        osc,
+        AudioNodeMessage::SetParam(
+            ParamType::Frequency,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Exponential, 880., 3.),
+        ),
+    );
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Stop(2.)),
+    );
+    let _ = context.resume();
+
+    // Print the level of the recording every half second
+    let mut sum = 0.;
+    let mut frames = 0;
+    while let Ok(buffer) = receiver.recv() {
+        if buffer.time >= 3. {
+            break;
+        }
+        let first_channel = buffer.data.len() / buffer.channels as usize;
+        sum += buffer.data[..first_channel]
+            .iter()
+            .map(|s| s * s)
+            .sum::<f32>();
+        frames += first_channel;
+        if frames as f32 >= buffer.sample_rate / 2. {
+            println!(
+                "{:.1}s: {} channels, RMS {:.3}",
+                buffer.time,
+                buffer.channels,
+                (sum / frames as f32).sqrt()
+            );
+            sum = 0.;
+            frames = 0;
+        }
+    }
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,92 @@
+extern crate servo_media;
+
+use servo_media::audio::gain_node::GainNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+
+    // A drone, which is removed explicitly at the end
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 110.;
+    let drone = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    let options = GainNodeOptions { gain: 0.2 };
+    let drone_gain = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+    context.connect_ports(drone.output(0), drone_gain.input(0));
+    context.connect_ports(drone_gain.output(0), dest.input(0));
+    context.message_node(
+        drone,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    let _ = context.resume();
+
+    // Short notes, whose nodes are released as soon as they are scheduled.
+    // They keep playing until they end, and are then removed from the graph.
+    for (i, &freq) in [440., 494., 523., 587., 659., 587., 523., 494.]
+        .iter()
+        .cycle()
+        .take(32)
+        .enumerate()
+    {
+        let start = context.current_time() + 0.05;
+        let mut options = OscillatorNodeOptions::default();
+        options.freq = freq;
+        let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+        let gain = context.create_node(
+            AudioNodeInit::GainNode(Default::default()),
+            Default::default(),
+        );
+        context.connect_ports(osc.output(0), gain.input(0));
+        context.connect_ports(gain.output(0), dest.input(0));
+        context.message_node(
+            gain,
+            AudioNodeMessage::SetParam(
+                ParamType::Gain,
+                UserAutomationEvent::SetTargetAtTime(0., start, 0.05),
+            ),
+        );
+        context.message_node(
+            osc,
+            AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(
+                start,
+            )),
+        );
+        context.message_node(
+            osc,
+            AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Stop(
+                start + 0.25,
+            )),
+        );
+        context.release_node(osc);
+        context.release_node(gain);
+        if i == 24 {
+            context.message_node(
+                drone_gain,
+                AudioNodeMessage::SetParam(
+                    ParamType::Gain,
+                    UserAutomationEvent::RampToValueAtTime(RampKind::Linear, 0., start + 1.),
+                ),
+            );
+        }
+        thread::sleep(time::Duration::from_millis(150));
+    }
+
+    context.remove_node(drone);
+    context.remove_node(drone_gain);
+    thread::sleep(time::Duration::from_millis(500));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
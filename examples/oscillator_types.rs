@@ -0,0 +1,50 @@
+extern crate servo_media;
+
+use servo_media::audio::gain_node::GainNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::{OscillatorNodeMessage, OscillatorType};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let osc = context.create_node(
+        AudioNodeInit::OscillatorNode(Default::default()),
+        Default::default(),
+    );
+    let options = GainNodeOptions { gain: 0.5 };
+    let gain = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+    let dest = context.dest_node();
+    context.connect_ports(osc.output(0), gain.input(0));
+    context.connect_ports(gain.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    for &oscillator_type in &[
+        OscillatorType::Square,
+        OscillatorType::Sawtooth,
+        OscillatorType::Triangle,
+    ] {
+        thread::sleep(time::Duration::from_millis(1000));
+        println!("Switching to {:?}", oscillator_type);
+        context.message_node(
+            osc,
+            AudioNodeMessage::OscillatorNode(OscillatorNodeMessage::SetOscillatorType(
+                oscillator_type,
+            )),
+        );
+    }
+    thread::sleep(time::Duration::from_millis(1000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
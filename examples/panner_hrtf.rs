@@ -0,0 +1,75 @@
+extern crate servo_media;
+
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::{OscillatorNodeOptions, OscillatorType};
+use servo_media::audio::panner_node::{PannerNodeMessage, PannerNodeOptions, PanningModel};
+use servo_media::audio::param::{ParamDir, ParamType, UserAutomationEvent};
+use servo_media::ServoMedia;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    // A sawtooth has plenty of high frequencies, which carry most of the
+    // direction cues
+    let mut options = OscillatorNodeOptions::default();
+    options.oscillator_type = OscillatorType::Sawtooth;
+    options.freq = 220.;
+    let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    let mut options = PannerNodeOptions::default();
+    options.panning_model = PanningModel::HRTF;
+    options.position_z = -1.;
+    let panner = context.create_node(AudioNodeInit::PannerNode(options), Default::default());
+    context.connect_ports(osc.output(0), panner.input(0));
+    context.connect_ports(panner.output(0), dest.input(0));
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+
+    // Go around the head in four seconds, then over it in four more
+    let steps = 64;
+    for step in 0..steps + 1 {
+        let angle = 2. * PI * step as f64 / steps as f64;
+        let time = 4. * step as f64 / steps as f64;
+        let (x, z) = (angle.sin() as f32, -angle.cos() as f32);
+        let (y, z2) = (angle.sin() as f32, -angle.cos() as f32);
+        for &(dir, value, at) in [
+            (ParamDir::X, x, time),
+            (ParamDir::Z, z, time),
+            (ParamDir::X, 0., time + 4.),
+            (ParamDir::Y, y, time + 4.),
+            (ParamDir::Z, z2, time + 4.),
+        ]
+        .iter()
+        {
+            context.message_node(
+                panner,
+                AudioNodeMessage::SetParam(
+                    ParamType::Position(dir),
+                    UserAutomationEvent::SetValueAtTime(value, at),
+                ),
+            );
+        }
+    }
+    let _ = context.resume();
+    thread::sleep(time::Duration::from_millis(8000));
+
+    // Go around again, with equal-power panning to compare
+    context.message_node(
+        panner,
+        AudioNodeMessage::PannerNode(PannerNodeMessage::SetPanningModel(PanningModel::EqualPower)),
+    );
+    thread::sleep(time::Duration::from_millis(4000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,88 @@
+extern crate servo_media;
+
+use servo_media::audio::gain_node::GainNodeOptions;
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let osc = context.create_node(
+        AudioNodeInit::OscillatorNode(Default::default()),
+        Default::default(),
+    );
+    let mut options = GainNodeOptions::default();
+    options.gain = 0.;
+    let gain = context.create_node(AudioNodeInit::GainNode(options), Default::default());
+    context.connect_ports(osc.output(0), gain.input(0));
+    context.connect_ports(gain.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    // 0.5s - 1.5s: Attack, decay and sustain envelope
+    context.message_node(
+        gain,
+        AudioNodeMessage::SetParam(
+            ParamType::Gain,
+            UserAutomationEvent::SetValueCurveAtTime(vec![0., 1., 0.6, 0.5, 0.5], 0.5, 1.),
+        ),
+    );
+    // This overlaps the curve, and is ignored
+    context.message_node(
+        gain,
+        AudioNodeMessage::SetParam(
+            ParamType::Gain,
+            UserAutomationEvent::SetValueAtTime(1., 1.),
+        ),
+    );
+    // 1.5s - 2s: Release, from the end of the curve
+    context.message_node(
+        gain,
+        AudioNodeMessage::SetParam(
+            ParamType::Gain,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Linear, 0., 2.),
+        ),
+    );
+    // 2.5s - 4.5s: Vibrato
+    context.message_node(
+        osc,
+        AudioNodeMessage::SetParam(
+            ParamType::Frequency,
+            UserAutomationEvent::SetValueCurveAtTime(
+                vec![440., 460., 440., 420., 440., 460., 440., 420., 440.],
+                2.5,
+                2.,
+            ),
+        ),
+    );
+    context.message_node(
+        gain,
+        AudioNodeMessage::SetParam(
+            ParamType::Gain,
+            UserAutomationEvent::SetValueCurveAtTime(vec![0., 0.5, 0.5, 0.5, 0.], 2.5, 2.),
+        ),
+    );
+    // 3s: Stop the vibrato and hold the frequency, while the gain curve
+    // carries on
+    context.message_node(
+        osc,
+        AudioNodeMessage::SetParam(
+            ParamType::Frequency,
+            UserAutomationEvent::CancelAndHoldAtTime(3.),
+        ),
+    );
+    thread::sleep(time::Duration::from_millis(5000));
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
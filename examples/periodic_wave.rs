@@ -0,0 +1,55 @@
+extern crate servo_media;
+
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::{
+    OscillatorNodeMessage, OscillatorNodeOptions, OscillatorType, PeriodicWaveOptions,
+};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    // An organ-like timbre, made of a few harmonics
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 220.;
+    options.oscillator_type = OscillatorType::Custom;
+    options.periodic_wave_options = Some(PeriodicWaveOptions {
+        real: vec![0.; 9],
+        imag: vec![0., 1., 0.8, 0., 0.6, 0., 0., 0., 0.4],
+        disable_normalization: false,
+    });
+    let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    context.connect_ports(osc.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    thread::sleep(time::Duration::from_millis(2000));
+    // A softer timbre, with decaying odd harmonics
+    let imag = (0..32)
+        .map(|n| if n % 2 == 1 { 1. / (n * n) as f32 } else { 0. })
+        .collect();
+    context.message_node(
+        osc,
+        AudioNodeMessage::OscillatorNode(OscillatorNodeMessage::SetPeriodicWave(
+            PeriodicWaveOptions {
+                real: vec![0.; 32],
+                imag,
+                disable_normalization: false,
+            },
+        )),
+    );
+    thread::sleep(time::Duration::from_millis(2000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,51 @@
+extern crate servo_media;
+
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::param::{ParamType, RampKind, UserAutomationEvent};
+use servo_media::audio::stereo_panner_node::StereoPannerNodeOptions;
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let osc = context.create_node(
+        AudioNodeInit::OscillatorNode(Default::default()),
+        Default::default(),
+    );
+    let options = StereoPannerNodeOptions { pan: -1. };
+    let panner = context.create_node(AudioNodeInit::StereoPannerNode(options), Default::default());
+    context.connect_ports(osc.output(0), panner.input(0));
+    context.connect_ports(panner.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    // Sweep from left to right and back
+    context.message_node(
+        panner,
+        AudioNodeMessage::SetParam(
+            ParamType::Pan,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Linear, 1., 2.),
+        ),
+    );
+    context.message_node(
+        panner,
+        AudioNodeMessage::SetParam(
+            ParamType::Pan,
+            UserAutomationEvent::RampToValueAtTime(RampKind::Linear, -1., 4.),
+        ),
+    );
+    thread::sleep(time::Duration::from_millis(4000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
@@ -0,0 +1,61 @@
+extern crate servo_media;
+
+use servo_media::audio::node::{AudioNodeInit, AudioNodeMessage, AudioScheduledSourceNodeMessage};
+use servo_media::audio::oscillator_node::OscillatorNodeOptions;
+use servo_media::audio::wave_shaper_node::{
+    OverSampleType, WaveShaperNodeMessage, WaveShaperNodeOptions,
+};
+use servo_media::ServoMedia;
+use std::sync::Arc;
+use std::{thread, time};
+
+/// A soft clipping distortion curve
+fn make_curve(amount: f32) -> Vec<f32> {
+    let len = 1024;
+    (0..len)
+        .map(|i| {
+            let x = 2. * i as f32 / (len - 1) as f32 - 1.;
+            (amount * x).tanh() / amount.tanh()
+        })
+        .collect()
+}
+
+fn run_example(servo_media: Arc<ServoMedia>) {
+    let context = servo_media.create_audio_context(Default::default());
+    let dest = context.dest_node();
+    let mut options = OscillatorNodeOptions::default();
+    options.freq = 220.;
+    let osc = context.create_node(AudioNodeInit::OscillatorNode(options), Default::default());
+    let options = WaveShaperNodeOptions {
+        curve: Some(make_curve(1.)),
+        oversample: OverSampleType::Quadruple,
+    };
+    let shaper = context.create_node(AudioNodeInit::WaveShaperNode(options), Default::default());
+    context.connect_ports(osc.output(0), shaper.input(0));
+    context.connect_ports(shaper.output(0), dest.input(0));
+    let _ = context.resume();
+    context.message_node(
+        osc,
+        AudioNodeMessage::AudioScheduledSourceNode(AudioScheduledSourceNodeMessage::Start(0.)),
+    );
+    // Distort more and more
+    for &amount in &[3., 10., 50.] {
+        thread::sleep(time::Duration::from_millis(1000));
+        context.message_node(
+            shaper,
+            AudioNodeMessage::WaveShaperNode(WaveShaperNodeMessage::SetCurve(Some(make_curve(
+                amount,
+            )))),
+        );
+    }
+    thread::sleep(time::Duration::from_millis(1000));
+    let _ = context.close();
+}
+
+fn main() {
+    if let Ok(servo_media) = ServoMedia::get() {
+        run_example(servo_media);
+    } else {
+        unreachable!();
+    }
+}
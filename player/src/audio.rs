@@ -0,0 +1,5 @@
+pub trait AudioRenderer: Send + 'static {
+    /// Render a chunk of decoded audio, given as one buffer of samples
+    /// per channel, at the given sample rate.
+    fn render(&mut self, channels: Vec<Vec<f32>>, sample_rate: u32);
+}
@@ -2,6 +2,7 @@ extern crate ipc_channel;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod audio;
 pub mod frame;
 pub mod metadata;
 
@@ -50,6 +51,12 @@ pub trait Player: Send {
         &self,
         renderer: Arc<Mutex<frame::FrameRenderer>>,
     ) -> Result<(), Self::Error>;
+    /// Route the audio of the player to the given renderer instead of
+    /// the audio output
+    fn register_audio_renderer(
+        &self,
+        renderer: Arc<Mutex<audio::AudioRenderer>>,
+    ) -> Result<(), Self::Error>;
 
     fn play(&self) -> Result<(), Self::Error>;
     fn pause(&self) -> Result<(), Self::Error>;
@@ -72,6 +79,9 @@ impl Player for DummyPlayer {
     fn register_frame_renderer(&self, _: Arc<Mutex<frame::FrameRenderer>>) -> Result<(), ()> {
         Ok(())
     }
+    fn register_audio_renderer(&self, _: Arc<Mutex<audio::AudioRenderer>>) -> Result<(), ()> {
+        Ok(())
+    }
 
     fn play(&self) -> Result<(), ()> {
         Ok(())
@@ -27,7 +27,7 @@ fn impl_audio_scheduled_source_node(ast: &syn::DeriveInput) -> quote::Tokens {
 
                 let frame_end = tick + Tick::FRAMES_PER_BLOCK;
                 if tick < start {
-                    if frame_end < start {
+                    if frame_end <= start {
                         ShouldPlay::No
                     } else {
                         let delta_start = start - tick;
@@ -64,6 +64,16 @@ fn impl_audio_scheduled_source_node(ast: &syn::DeriveInput) -> quote::Tokens {
                 }
             }
 
+            /// Whether the source has been started and is still scheduled
+            /// to play at `tick` or later
+            fn plays_after(&self, tick: Tick) -> bool {
+                match (self.start_at, self.stop_at) {
+                    (None, _) => false,
+                    (Some(start), Some(stop)) => stop > start && stop > tick,
+                    (Some(_), None) => true,
+                }
+            }
+
             fn start(&mut self, tick: Tick) -> bool {
                 // We can only allow a single call to `start` and always before
                 // any `stop` calls.